@@ -16,6 +16,13 @@ pub enum InitialPopulation {
     AlternatingThird,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Capacities {
+    Even,
+    List(Vec<usize>),
+    Geometric { ratio: f64 },
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct InitConfig {
     // max ticks, unlimited if None (=100000)
@@ -25,9 +32,27 @@ pub struct InitConfig {
     pub patches:     usize,
     pub individuals: usize,
     pub loci:        usize,
+    // amount of traits the loci are divided over (=1)
+    pub traits:      usize,
 
     // diploid or haploid
     pub diploid:         bool,
+    // how the loci combine into the phenotype
+    pub genotype_map: GenotypeMap,
+    // crossover probability between every pair of adjacent loci, derived from rec if None
+    pub recombination_map: Option<Vec<f64>>,
+    // carrying capacity of every patch
+    pub capacities: Capacities,
+
+    // random number generator seed, random if None
+    pub seed: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum GenotypeMap {
+    Additive,
+    Dominance { h: f64 },
+    Epistasis { epsilon: f64 },
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -35,12 +60,100 @@ pub enum Environment {
     Random,
     AlternatingHalf,
     AlternatingThird,
-    SineSlow,
-    SineMedium,
-    SineFast,
+    Sine {
+        period:       f64,
+        amplitude:    f64,
+        phase_spread: f64,
+        mean:         f64,
+    },
     RandomWalk,
     Constant,
     ConstantWithJumps,
+    Correlated {
+        variance:        f64,
+        autocorrelation: f64,
+        correlation:     f64,
+    },
+    FromSeries { path: String, repeat: bool },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Dispersal {
+    Global,
+    SteppingStone { circular: bool },
+    Lattice { width: usize, circular: bool },
+    Kernel { scale: f64 },
+    Matrix(Vec<Vec<f64>>),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum FitnessFunction {
+    Gaussian,
+    Quadratic,
+    Truncation,
+    Plateau { width: f64 },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum MutationModel {
+    Gaussian,
+    DiscreteGaussian,
+    Stepwise,
+    HouseOfCards,
+    Bounded { min: f64, max: f64 },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum MarkerModel {
+    InfiniteAlleles,
+    Stepwise,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Markers {
+    // amount of neutral marker loci on a haplotype
+    pub loci:  usize,
+    // mutation probability of every marker allele
+    pub mu:    f64,
+    // how a mutation changes a marker allele
+    pub model: MarkerModel,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Plasticity {
+    // mutational effect size of the reaction norm slope
+    pub sigma:     f64,
+    // standard deviation of the noise on the environmental cue (=0)
+    pub cue_noise: f64,
+    // amount of ticks the environmental cue lags behind the environment (=0)
+    pub cue_lag:   usize,
+    // cost of plasticity, fitness is multiplied by e^(-cost*slope^2) (=0)
+    pub cost:      f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Dioecy {
+    // the optimum of males lies offset above and that of females offset below the optimum (=0)
+    pub offset: f64,
+    // selection strength (standard deviation) of males, selection_sigma if None, a selection
+    // covariance matrix is scaled by (sigma/selection_sigma)^2 for males
+    pub sigma:  Option<f64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SeedBank {
+    // probability that an offspring becomes dormant instead of competing for a spot
+    pub dormancy:    f64,
+    // probability that a dormant individual germinates every tick
+    pub germination: f64,
+    // probability that a dormant individual dies every tick
+    pub decay:       f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum PopulationDynamics {
+    BevertonHolt,
+    Ricker,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -51,15 +164,62 @@ pub struct Config {
     pub mutation_sigma:  f64,
     // bin size for mutational effects (=0.01)
     pub mutation_step:   f64,
+    // how a mutation changes the allelic value
+    pub mutation_model: MutationModel,
+    // mutation probability of every locus, mutation_mu for all loci if None
+    pub mutation_rates: Option<Vec<f64>>,
+    // mutational effect size of the heritable mutation probability, everybody mutates with
+    // mutation_mu if None
+    pub mutator_sigma: Option<f64>,
+    // neutral marker loci that are inherited and migrate without being selected, no markers if
+    // None
+    pub markers: Option<Markers>,
     // recombinational probality (=0.01)
     pub rec:             f64,
+    // probability that an individual fertilises itself (=0)
+    pub selfing: f64,
+    // strength a of assortative mating, mates at phenotypic distance d are preferred with weight
+    // e^(-a*d^2) (=0)
+    pub assortative_mating: f64,
     // selection strength (standard deviation)
     pub selection_sigma: f64,
+    // shape of the fitness function around the optimum
+    pub fitness:         FitnessFunction,
+    // reproductive success at the optimum (=1)
+    pub r_max:           f64,
+    // width of the competition kernel, no frequency dependent competition if None
+    pub competition_sigma: Option<f64>,
+    // offset and slope of the optimum of every trait on its own component of the environment,
+    // every trait follows its component if empty
+    pub optima: Vec<(f64, f64)>,
+    // covariance matrix of the selection surface over the traits, selection_sigma^2 on the
+    // diagonal if None
+    pub selection_covariance: Option<Vec<Vec<f64>>>,
+    // separate sexes with sex-specific selection, hermaphrodites if None
+    pub dioecy: Option<Dioecy>,
+    // reaction norm of the first trait on an environmental cue, no plasticity if None
+    pub plasticity: Option<Plasticity>,
+    // variance of the developmental noise on the phenotype (=0)
+    pub developmental_variance: f64,
     // generation overlap
     pub gamma:           f64,
+    // survival probability of every age class, the last one also holds for all older ages, gamma
+    // for every age if None
+    pub survival: Option<Vec<f64>>,
+    // dormant pool of offspring in every patch, no seed bank if None
+    pub seed_bank: Option<SeedBank>,
     // dispersal parameter
     pub m:               f64,
-    // environment update function
+    // mutational effect size of the heritable dispersal probability, everybody disperses with m
+    // if None
+    pub dispersal_sigma: Option<f64>,
+    // fraction of hard selection between 0 and 1, 0 is soft and 1 is hard selection (=0)
+    pub hard_selection:  f64,
+    // regulation of the patch sizes by mean fitness, the patches are kept full if None
+    pub population_dynamics: Option<PopulationDynamics>,
+    // dispersal topology between the patches
+    pub dispersal:       Dispersal,
+    // environment update function, every trait has its own component of the environment
     pub environment:     Environment,
 }
 
@@ -0,0 +1,77 @@
+use seed::{prelude::*, *};
+
+use crate::api::{make_suggestions, Capacities};
+use crate::fields::{Field, InputField, SelectField};
+
+#[derive(Clone, Debug)]
+pub enum Msg {
+	Kind(<SelectField as Field>::Msg),
+	List(<InputField<String> as Field>::Msg),
+	Ratio(<InputField<f64> as Field>::Msg),
+}
+
+pub struct CapacitiesForm {
+	kind: SelectField,
+	list: InputField<String>,
+	ratio: InputField<f64>,
+}
+
+/// parse carrying capacities separated by ',', which must be at least 1
+fn parse_capacities(text: &str) -> Option<Vec<usize>> {
+	text.split(',')
+		.map(|x| x.trim().parse::<usize>().ok())
+		.map(|x| x.filter(|&x| x > 0))
+		.collect()
+}
+
+impl CapacitiesForm {
+	pub fn new() -> Self {
+		let kind_suggestions =
+			make_suggestions(&["Divided evenly", "Capacity per patch", "Geometric series"]);
+
+		Self {
+			kind: SelectField::new("Carrying capacities", kind_suggestions, false)
+				.with_initial(Some(0)),
+			list: InputField::new("Capacity per patch", false)
+				.with_placeholder("separated by ','")
+				.with_validator(|value: &String| {
+					parse_capacities(value)
+						.is_none()
+						.then(|| "Capacities must be whole numbers of at least 1.".to_string())
+				}),
+			ratio: InputField::new("Ratio between neighbouring patches", false)
+				.with_initial(Some(1.0))
+				.with_validator(|&value| {
+					(value <= 0.0).then(|| "Number must be strictly positive.".to_string())
+				}),
+		}
+	}
+
+	pub fn update(&mut self, msg: Msg, orders: &mut impl Orders<Msg>) -> bool {
+		match msg {
+			Msg::Kind(msg) => self.kind.update(msg, &mut orders.proxy(Msg::Kind)),
+			Msg::List(msg) => self.list.update(msg, &mut orders.proxy(Msg::List)),
+			Msg::Ratio(msg) => self.ratio.update(msg, &mut orders.proxy(Msg::Ratio)),
+		}
+	}
+
+	pub fn extract(&self) -> Option<Capacities> {
+		match self.kind.value(true) {
+			Some(0) => Some(Capacities::Even),
+			Some(1) => Some(Capacities::List(parse_capacities(&self.list.value(true)?)?)),
+			Some(2) => Some(Capacities::Geometric {
+				ratio: self.ratio.value(true)?,
+			}),
+			Some(_) | None => None,
+		}
+	}
+
+	pub fn view(&self, disabled: bool) -> Node<Msg> {
+		let kind = self.kind.value(false);
+		div![
+			self.kind.view(disabled).map_msg(Msg::Kind),
+			IF!(kind == Some(1) => self.list.view(disabled).map_msg(Msg::List)),
+			IF!(kind == Some(2) => self.ratio.view(disabled).map_msg(Msg::Ratio)),
+		]
+	}
+}
@@ -1,57 +1,124 @@
 use seed::{prelude::*, *};
 
-use crate::api::{make_suggestions, Config, Environment, InitConfig, Suggestion, Suggestions};
+use crate::api::{Config, InitConfig, Suggestion, Suggestions};
 use crate::components::Button;
 use crate::fields::slider::SliderField;
 use crate::fields::{CheckboxField, Field, InputField, SelectField};
+use crate::forms::dispersal::{self, DispersalForm};
+use crate::forms::environment::{self, EnvironmentForm};
+use crate::forms::fitness::{self, FitnessForm};
+use crate::forms::mutation::{self, MutationForm};
+use crate::forms::traits::{self, TraitsForm};
+use crate::forms::markers::{self, MarkersForm};
+use crate::forms::dioecy::{self, DioecyForm};
+use crate::forms::dynamics::{self, DynamicsForm};
+use crate::forms::seed_bank::{self, SeedBankForm};
+use crate::forms::plasticity::{self, PlasticityForm};
 
 #[derive(Clone, Debug)]
 pub enum Msg {
 	MutationMu(<SliderField as Field>::Msg),
 	MutationSigma(<SliderField as Field>::Msg),
 	MutationStep(<SliderField as Field>::Msg),
-	Environment(<SelectField as Field>::Msg),
+	MutatorSigma(<InputField<f64> as Field>::Msg),
+	Markers(markers::Msg),
+	Mutation(mutation::Msg),
+	Environment(environment::Msg),
 	Rec(<SliderField as Field>::Msg),
+	Selfing(<SliderField as Field>::Msg),
+	AssortativeMating(<InputField<f64> as Field>::Msg),
 	SelectionSigma(<SliderField as Field>::Msg),
+	Fitness(fitness::Msg),
+	RMax(<SliderField as Field>::Msg),
+	CompetitionSigma(<InputField<f64> as Field>::Msg),
+	Dioecy(dioecy::Msg),
+	DevelopmentalVariance(<SliderField as Field>::Msg),
+	Plasticity(plasticity::Msg),
+	Traits(traits::Msg),
 	Gamma(<SliderField as Field>::Msg),
+	Survival(<InputField<String> as Field>::Msg),
 	M(<SliderField as Field>::Msg),
+	SeedBank(seed_bank::Msg),
+	DispersalSigma(<InputField<f64> as Field>::Msg),
+	HardSelection(<SliderField as Field>::Msg),
+	Dynamics(dynamics::Msg),
+	Dispersal(dispersal::Msg),
 }
 
 pub struct ConfigForm {
 	mutation_mu: SliderField,
 	mutation_sigma: SliderField,
 	mutation_step: SliderField,
+	mutator_sigma: InputField<f64>,
+	markers: MarkersForm,
+	mutation: MutationForm,
 	rec: SliderField,
-	environment: SelectField,
+	selfing: SliderField,
+	assortative_mating: InputField<f64>,
+	environment: EnvironmentForm,
 	selection_sigma: SliderField,
+	fitness: FitnessForm,
+	r_max: SliderField,
+	competition_sigma: InputField<f64>,
+	dioecy: DioecyForm,
+	developmental_variance: SliderField,
+	plasticity: PlasticityForm,
+	traits: TraitsForm,
 	gamma: SliderField,
+	survival: InputField<String>,
 	m: SliderField,
+	seed_bank: SeedBankForm,
+	dispersal_sigma: InputField<f64>,
+	hard_selection: SliderField,
+	population_dynamics: DynamicsForm,
+	dispersal: DispersalForm,
 }
 
 impl ConfigForm {
 	pub fn new() -> Self {
-		let kind_suggestions = make_suggestions(&[
-			"Random",
-			"Alternating with 50% chance",
-			"Alternating with 67% chance",
-			"Slow sinusoid with patch offset",
-			"Medium sinusoid with patch offset",
-			"Fast sinusoid with patch offset",
-			"Random walk",
-			"Constant",
-			"Constant with jumps"
-		]);
-
 		Self {
 			mutation_mu: SliderField::new("Mutation probability", 0.0..1., 0.01),
 			mutation_sigma: SliderField::new("Mutational effect", 0.0..1., 0.01),
 			mutation_step: SliderField::new("Mutational step size", 0.01..1., 0.01),
+			mutator_sigma: InputField::new("Mutator mutational effect", true)
+				.with_placeholder("leave empty for a fixed mutation probability")
+				.with_validator(|&value| (value <= 0.0).then(|| "Number must be strictly positive.".to_string())),
+			markers: MarkersForm::new(),
+			mutation: MutationForm::new(),
 			rec: SliderField::new("Recombination probability", 0.0..1., 0.01),
-			environment: SelectField::new("Environment function", kind_suggestions, false)
-				.with_initial(Some(1)),
+			selfing: SliderField::new("Selfing rate", 0.0..1., 0.0),
+			assortative_mating: InputField::new("Assortative mating strength", false)
+				.with_initial(Some(0.0))
+				.with_validator(|&value| (value < 0.0).then(|| "Number cannot be negative.".to_string())),
+			environment: EnvironmentForm::new(),
 			selection_sigma: SliderField::new("Selection strength", 0.01..1., 0.3),
+			fitness: FitnessForm::new(),
+			traits: TraitsForm::new(),
+			r_max: SliderField::new("Maximal reproductive success", 0.1..10., 1.0),
+			competition_sigma: InputField::new("Competition width", true)
+				.with_placeholder("leave empty for no competition")
+				.with_validator(|&value| {
+					(value <= 0.0).then(|| "Number must be strictly positive.".to_string())
+				}),
+			dioecy: DioecyForm::new(),
+			developmental_variance: SliderField::new("Developmental noise variance", 0.0..1., 0.0),
+			plasticity: PlasticityForm::new(),
 			gamma: SliderField::new("Generation Overlap", 0.0..1., 0.0),
+			survival: InputField::new("Survival probability per age", true)
+				.with_placeholder("separated by ',', leave empty to use the generation overlap for every age")
+				.with_validator(|value: &String| {
+					mutation::parse_rates(value)
+						.is_none()
+						.then(|| "Probabilities must lie between 0 and 1.".to_string())
+				}),
 			m: SliderField::new("Dispersal probability", 0.0..1., 1.0),
+			seed_bank: SeedBankForm::new(),
+			dispersal_sigma: InputField::new("Dispersal mutational effect", true)
+				.with_placeholder("leave empty for a fixed dispersal probability")
+				.with_validator(|&value| (value <= 0.0).then(|| "Number must be strictly positive.".to_string())),
+			hard_selection: SliderField::new("Hard selection", 0.0..1., 0.0),
+			population_dynamics: DynamicsForm::new(),
+			dispersal: DispersalForm::new(),
 		}
 	}
 
@@ -67,15 +134,51 @@ impl ConfigForm {
 			Msg::MutationStep(msg) => self
 				.mutation_step
 				.update(msg, &mut orders.proxy(Msg::MutationStep)),
+			Msg::MutatorSigma(msg) => self
+				.mutator_sigma
+				.update(msg, &mut orders.proxy(Msg::MutatorSigma)),
+			Msg::Markers(msg) => self.markers.update(msg, &mut orders.proxy(Msg::Markers)),
+			Msg::Mutation(msg) => self
+				.mutation
+				.update(msg, &mut orders.proxy(Msg::Mutation)),
 			Msg::Environment(msg) => self
 				.environment
 				.update(msg, &mut orders.proxy(Msg::Environment)),
 			Msg::Rec(msg) => self.rec.update(msg, &mut orders.proxy(Msg::Rec)),
+			Msg::Selfing(msg) => self.selfing.update(msg, &mut orders.proxy(Msg::Selfing)),
+			Msg::AssortativeMating(msg) => self
+				.assortative_mating
+				.update(msg, &mut orders.proxy(Msg::AssortativeMating)),
 			Msg::SelectionSigma(msg) => self
 				.selection_sigma
 				.update(msg, &mut orders.proxy(Msg::SelectionSigma)),
+			Msg::Fitness(msg) => self
+				.fitness
+				.update(msg, &mut orders.proxy(Msg::Fitness)),
+			Msg::RMax(msg) => self.r_max.update(msg, &mut orders.proxy(Msg::RMax)),
+			Msg::CompetitionSigma(msg) => self
+				.competition_sigma
+				.update(msg, &mut orders.proxy(Msg::CompetitionSigma)),
+			Msg::Dioecy(msg) => self.dioecy.update(msg, &mut orders.proxy(Msg::Dioecy)),
+			Msg::DevelopmentalVariance(msg) => self
+				.developmental_variance
+				.update(msg, &mut orders.proxy(Msg::DevelopmentalVariance)),
+			Msg::Plasticity(msg) => self.plasticity.update(msg, &mut orders.proxy(Msg::Plasticity)),
+			Msg::Traits(msg) => self.traits.update(msg, &mut orders.proxy(Msg::Traits)),
 			Msg::Gamma(msg) => self.gamma.update(msg, &mut orders.proxy(Msg::Gamma)),
+			Msg::Survival(msg) => self.survival.update(msg, &mut orders.proxy(Msg::Survival)),
 			Msg::M(msg) => self.m.update(msg, &mut orders.proxy(Msg::M)),
+			Msg::SeedBank(msg) => self.seed_bank.update(msg, &mut orders.proxy(Msg::SeedBank)),
+			Msg::DispersalSigma(msg) => self
+				.dispersal_sigma
+				.update(msg, &mut orders.proxy(Msg::DispersalSigma)),
+			Msg::HardSelection(msg) => self
+				.hard_selection
+				.update(msg, &mut orders.proxy(Msg::HardSelection)),
+			Msg::Dynamics(msg) => self.population_dynamics.update(msg, &mut orders.proxy(Msg::Dynamics)),
+			Msg::Dispersal(msg) => self
+				.dispersal
+				.update(msg, &mut orders.proxy(Msg::Dispersal)),
 		}
 	}
 
@@ -83,52 +186,104 @@ impl ConfigForm {
 		let mutation_mu = self.mutation_mu.value(true);
 		let mutation_sigma = self.mutation_sigma.value(true);
 		let mutation_step = self.mutation_step.value(true);
+		let mutator_sigma = self.mutator_sigma.value(true);
+		let markers = self.markers.extract()?;
+		let (mutation_model, mutation_rates) = self.mutation.extract()?;
 		let rec = self.rec.value(true);
+		let selfing = self.selfing.value(true);
+		let assortative_mating = self.assortative_mating.value(true);
 		let selection_sigma = self.selection_sigma.value(true);
+		let fitness = self.fitness.extract();
+		let r_max = self.r_max.value(true);
+		let competition_sigma = self.competition_sigma.value(true);
+		let dioecy = self.dioecy.extract()?;
+		let developmental_variance = self.developmental_variance.value(true);
+		let plasticity = self.plasticity.extract()?;
+		let (optima, selection_covariance) = self.traits.extract()?;
 		let gamma = self.gamma.value(true);
-		let m = self.m.value(true);
-		let environment = self.environment.value(true);
-
-		let environment = match environment {
-			Some(0) => Environment::Random,
-			Some(1) => Environment::AlternatingHalf,
-			Some(2) => Environment::AlternatingThird,
-			Some(3) => Environment::SineSlow,
-			Some(4) => Environment::SineMedium,
-			Some(5) => Environment::SineFast,
-			Some(6) => Environment::RandomWalk,
-			Some(7) => Environment::Constant,
-			Some(8) => Environment::ConstantWithJumps,
-			Some(_) | None => return None,
+		let survival = match self.survival.value(true) {
+			Some(text) => Some(mutation::parse_rates(&text)?),
+			None => None,
 		};
+		let m = self.m.value(true);
+		let seed_bank = self.seed_bank.extract()?;
+		let dispersal_sigma = self.dispersal_sigma.value(true);
+		let hard_selection = self.hard_selection.value(true);
+		let population_dynamics = self.population_dynamics.extract();
+		let dispersal = self.dispersal.extract();
+		let environment = self.environment.extract();
 
 		Some(Config {
 			mutation_mu: mutation_mu?,
 			mutation_sigma: mutation_sigma?,
 			mutation_step: mutation_step?,
+			mutator_sigma,
+			markers,
+			mutation_model,
+			mutation_rates,
 			rec: rec?,
+			selfing: selfing?,
+			assortative_mating: assortative_mating?,
 			selection_sigma: selection_sigma?,
+			fitness: fitness?,
+			r_max: r_max?,
+			competition_sigma,
+			dioecy,
+			developmental_variance: developmental_variance?,
+			plasticity,
+			optima,
+			selection_covariance,
 			gamma: gamma?,
+			survival,
 			m: m?,
-			environment,
+			seed_bank,
+			dispersal_sigma,
+			hard_selection: hard_selection?,
+			population_dynamics: population_dynamics?,
+			dispersal: dispersal?,
+			environment: environment?,
 		})
 	}
 
 	pub fn view(&self) -> Node<Msg> {
 		div![
 			self.rec.view(false).map_msg(Msg::Rec),
+			self.selfing.view(false).map_msg(Msg::Selfing),
+			self.assortative_mating.view(false).map_msg(Msg::AssortativeMating),
 			hr![],
-			self.environment.view(false).map_msg(Msg::Environment),
+			self.environment.view().map_msg(Msg::Environment),
 			hr![],
 			self.selection_sigma
 				.view(false)
 				.map_msg(Msg::SelectionSigma),
+			self.fitness.view().map_msg(Msg::Fitness),
+			self.r_max.view(false).map_msg(Msg::RMax),
+			self.competition_sigma
+				.view(false)
+				.map_msg(Msg::CompetitionSigma),
+			self.traits.view().map_msg(Msg::Traits),
+			self.plasticity.view().map_msg(Msg::Plasticity),
+			self.dioecy.view().map_msg(Msg::Dioecy),
+			self.developmental_variance.view(false).map_msg(Msg::DevelopmentalVariance),
 			self.gamma.view(false).map_msg(Msg::Gamma),
+			self.survival.view(false).map_msg(Msg::Survival),
+			self.seed_bank.view().map_msg(Msg::SeedBank),
 			self.m.view(false).map_msg(Msg::M),
+			self.dispersal_sigma
+				.view(false)
+				.map_msg(Msg::DispersalSigma),
+			self.hard_selection.view(false).map_msg(Msg::HardSelection),
+			self.population_dynamics.view().map_msg(Msg::Dynamics),
+			self.dispersal.view().map_msg(Msg::Dispersal),
 			hr![],
 			self.mutation_mu.view(false).map_msg(Msg::MutationMu),
 			self.mutation_sigma.view(false).map_msg(Msg::MutationSigma),
 			self.mutation_step.view(false).map_msg(Msg::MutationStep),
+			self.mutation.view().map_msg(Msg::Mutation),
+			self.mutator_sigma
+				.view(false)
+				.map_msg(Msg::MutatorSigma),
+			self.markers.view().map_msg(Msg::Markers),
 		]
 	}
 }
@@ -0,0 +1,53 @@
+use seed::{prelude::*, *};
+
+use crate::api::Dioecy;
+use crate::fields::{Field, InputField};
+
+#[derive(Clone, Debug)]
+pub enum Msg {
+	Offset(<InputField<f64> as Field>::Msg),
+	Sigma(<InputField<f64> as Field>::Msg),
+}
+
+pub struct DioecyForm {
+	offset: InputField<f64>,
+	sigma: InputField<f64>,
+}
+
+impl DioecyForm {
+	pub fn new() -> Self {
+		Self {
+			offset: InputField::new("Offset between the optima of the sexes", true)
+				.with_placeholder("leave empty for hermaphrodites"),
+			sigma: InputField::new("Selection strength of males", true)
+				.with_placeholder("leave empty for the same selection strength")
+				.with_validator(|&value| (value <= 0.0).then(|| "Number must be strictly positive.".to_string())),
+		}
+	}
+
+	pub fn update(&mut self, msg: Msg, orders: &mut impl Orders<Msg>) -> bool {
+		match msg {
+			Msg::Offset(msg) => self.offset.update(msg, &mut orders.proxy(Msg::Offset)),
+			Msg::Sigma(msg) => self.sigma.update(msg, &mut orders.proxy(Msg::Sigma)),
+		}
+	}
+
+	/// the separate sexes, None if the form is invalid and Some(None) for hermaphrodites
+	pub fn extract(&self) -> Option<Option<Dioecy>> {
+		let offset = match self.offset.value(true) {
+			Some(offset) => offset,
+			None => return Some(None),
+		};
+		Some(Some(Dioecy {
+			offset,
+			sigma: self.sigma.value(true),
+		}))
+	}
+
+	pub fn view(&self) -> Node<Msg> {
+		div![
+			self.offset.view(false).map_msg(Msg::Offset),
+			IF!(self.offset.value(false).is_some() => self.sigma.view(false).map_msg(Msg::Sigma)),
+		]
+	}
+}
@@ -0,0 +1,106 @@
+use seed::{prelude::*, *};
+
+use crate::api::{make_suggestions, Dispersal};
+use crate::fields::{Field, InputField, SelectField};
+
+#[derive(Clone, Debug)]
+pub enum Msg {
+	Kind(<SelectField as Field>::Msg),
+	Width(<InputField<u64> as Field>::Msg),
+	Scale(<InputField<f64> as Field>::Msg),
+	Matrix(<InputField<String> as Field>::Msg),
+}
+
+pub struct DispersalForm {
+	kind: SelectField,
+	width: InputField<u64>,
+	scale: InputField<f64>,
+	matrix: InputField<String>,
+}
+
+/// parse a matrix written as rows separated by ';' and values separated by ','
+pub fn parse_matrix(text: &str) -> Option<Vec<Vec<f64>>> {
+	text.split(';')
+		.map(|row| {
+			row.split(',')
+				.map(|x| x.trim().parse::<f64>().ok())
+				.collect::<Option<Vec<_>>>()
+		})
+		.collect()
+}
+
+impl DispersalForm {
+	pub fn new() -> Self {
+		let kind_suggestions = make_suggestions(&[
+			"Global (island model)",
+			"Stepping stone on a chain",
+			"Stepping stone on a ring",
+			"Lattice",
+			"Lattice wrapped around (torus)",
+			"Distance kernel",
+			"Migration matrix",
+		]);
+
+		Self {
+			kind: SelectField::new("Dispersal topology", kind_suggestions, false)
+				.with_initial(Some(0)),
+			width: InputField::new("Lattice width", false)
+				.with_initial(Some(2))
+				.with_validator(|&value| {
+					(value == 0).then(|| "Number must be strictly positive.".to_string())
+				}),
+			scale: InputField::new("Kernel scale", false)
+				.with_initial(Some(1.0))
+				.with_validator(|&value| {
+					(value <= 0.0).then(|| "Number must be strictly positive.".to_string())
+				}),
+			matrix: InputField::new("Migration matrix", false)
+				.with_placeholder("rows separated by ';', values by ','")
+				.with_validator(|value: &String| {
+					parse_matrix(value)
+						.is_none()
+						.then(|| "Matrix must only contain numbers.".to_string())
+				}),
+		}
+	}
+
+	pub fn update(&mut self, msg: Msg, orders: &mut impl Orders<Msg>) -> bool {
+		match msg {
+			Msg::Kind(msg) => self.kind.update(msg, &mut orders.proxy(Msg::Kind)),
+			Msg::Width(msg) => self.width.update(msg, &mut orders.proxy(Msg::Width)),
+			Msg::Scale(msg) => self.scale.update(msg, &mut orders.proxy(Msg::Scale)),
+			Msg::Matrix(msg) => self.matrix.update(msg, &mut orders.proxy(Msg::Matrix)),
+		}
+	}
+
+	pub fn extract(&self) -> Option<Dispersal> {
+		match self.kind.value(true) {
+			Some(0) => Some(Dispersal::Global),
+			Some(1) => Some(Dispersal::SteppingStone { circular: false }),
+			Some(2) => Some(Dispersal::SteppingStone { circular: true }),
+			Some(3) => Some(Dispersal::Lattice {
+				width: self.width.value(true)? as usize,
+				circular: false,
+			}),
+			Some(4) => Some(Dispersal::Lattice {
+				width: self.width.value(true)? as usize,
+				circular: true,
+			}),
+			Some(5) => Some(Dispersal::Kernel {
+				scale: self.scale.value(true)?,
+			}),
+			Some(6) => Some(Dispersal::Matrix(parse_matrix(&self.matrix.value(true)?)?)),
+			Some(_) | None => None,
+		}
+	}
+
+	pub fn view(&self) -> Node<Msg> {
+		let kind = self.kind.value(false);
+		div![
+			self.kind.view(false).map_msg(Msg::Kind),
+			IF!(matches!(kind, Some(3) | Some(4)) => self.width.view(false).map_msg(Msg::Width)),
+			IF!(kind == Some(5) => self.scale.view(false).map_msg(Msg::Scale)),
+			IF!(kind == Some(6) => self.matrix.view(false).map_msg(Msg::Matrix)),
+		]
+	}
+}
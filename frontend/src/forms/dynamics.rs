@@ -0,0 +1,49 @@
+use seed::{prelude::*, *};
+
+use crate::api::{make_suggestions, PopulationDynamics};
+use crate::fields::{Field, SelectField};
+
+#[derive(Clone, Debug)]
+pub enum Msg {
+	Kind(<SelectField as Field>::Msg),
+}
+
+pub struct DynamicsForm {
+	kind: SelectField,
+}
+
+impl DynamicsForm {
+	pub fn new() -> Self {
+		let kind_suggestions = make_suggestions(&[
+			"None (patches are kept full)",
+			"Beverton-Holt",
+			"Ricker",
+		]);
+
+		Self {
+			kind: SelectField::new("Population dynamics", kind_suggestions, false)
+				.with_initial(Some(0)),
+		}
+	}
+
+	pub fn update(&mut self, msg: Msg, orders: &mut impl Orders<Msg>) -> bool {
+		match msg {
+			Msg::Kind(msg) => self.kind.update(msg, &mut orders.proxy(Msg::Kind)),
+		}
+	}
+
+	/// the population dynamics, None if the form is invalid and Some(None) if the patches are
+	/// kept full
+	pub fn extract(&self) -> Option<Option<PopulationDynamics>> {
+		match self.kind.value(true) {
+			Some(0) => Some(None),
+			Some(1) => Some(Some(PopulationDynamics::BevertonHolt)),
+			Some(2) => Some(Some(PopulationDynamics::Ricker)),
+			Some(_) | None => None,
+		}
+	}
+
+	pub fn view(&self) -> Node<Msg> {
+		div![self.kind.view(false).map_msg(Msg::Kind)]
+	}
+}
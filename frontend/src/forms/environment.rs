@@ -0,0 +1,150 @@
+use seed::{prelude::*, *};
+
+use crate::api::{make_suggestions, Environment};
+use crate::fields::{Field, InputField, SelectField};
+
+#[derive(Clone, Debug)]
+pub enum Msg {
+	Kind(<SelectField as Field>::Msg),
+	Period(<InputField<f64> as Field>::Msg),
+	Amplitude(<InputField<f64> as Field>::Msg),
+	PhaseSpread(<InputField<f64> as Field>::Msg),
+	Mean(<InputField<f64> as Field>::Msg),
+	Variance(<InputField<f64> as Field>::Msg),
+	Autocorrelation(<InputField<f64> as Field>::Msg),
+	Correlation(<InputField<f64> as Field>::Msg),
+	Path(<InputField<String> as Field>::Msg),
+	Repeat(<InputField<bool> as Field>::Msg),
+}
+
+pub struct EnvironmentForm {
+	kind: SelectField,
+	period: InputField<f64>,
+	amplitude: InputField<f64>,
+	phase_spread: InputField<f64>,
+	mean: InputField<f64>,
+	variance: InputField<f64>,
+	autocorrelation: InputField<f64>,
+	correlation: InputField<f64>,
+	path: InputField<String>,
+	repeat: InputField<bool>,
+}
+
+impl EnvironmentForm {
+	pub fn new() -> Self {
+		let kind_suggestions = make_suggestions(&[
+			"Random",
+			"Alternating with 50% chance",
+			"Alternating with 67% chance",
+			"Sinusoid with patch offset",
+			"Random walk",
+			"Constant",
+			"Constant with jumps",
+			"Correlated fluctuations",
+			"Time series from a csv file",
+		]);
+
+		Self {
+			kind: SelectField::new("Environment function", kind_suggestions, false)
+				.with_initial(Some(1)),
+			period: InputField::new("Period (ticks)", false)
+				.with_initial(Some(20_000.0))
+				.with_validator(|&value| {
+					(value <= 0.0).then(|| "Number must be strictly positive.".to_string())
+				}),
+			amplitude: InputField::new("Amplitude", false).with_initial(Some(0.5)),
+			phase_spread: InputField::new("Phase spread (cycles)", false).with_initial(Some(1.0)),
+			mean: InputField::new("Mean", false).with_initial(Some(0.0)),
+			variance: InputField::new("Environmental variance", false)
+				.with_initial(Some(0.1))
+				.with_validator(|&value| {
+					(value < 0.0).then(|| "Number cannot be negative.".to_string())
+				}),
+			autocorrelation: InputField::new("Temporal autocorrelation", false)
+				.with_initial(Some(0.9))
+				.with_validator(|&value| {
+					(!(-1.0..=1.0).contains(&value))
+						.then(|| "Number must be between -1 and 1.".to_string())
+				}),
+			correlation: InputField::new("Correlation between patches", false)
+				.with_initial(Some(0.5))
+				.with_validator(|&value| {
+					(!(0.0..=1.0).contains(&value))
+						.then(|| "Number must be between 0 and 1.".to_string())
+				}),
+			path: InputField::new("Series file in the series directory", false)
+				.with_placeholder("a row per tick, a column per patch (and trait)"),
+			repeat: InputField::new("Loop the series", false).with_initial(Some(true)),
+		}
+	}
+
+	pub fn update(&mut self, msg: Msg, orders: &mut impl Orders<Msg>) -> bool {
+		match msg {
+			Msg::Kind(msg) => self.kind.update(msg, &mut orders.proxy(Msg::Kind)),
+			Msg::Period(msg) => self.period.update(msg, &mut orders.proxy(Msg::Period)),
+			Msg::Amplitude(msg) => self.amplitude.update(msg, &mut orders.proxy(Msg::Amplitude)),
+			Msg::PhaseSpread(msg) => self
+				.phase_spread
+				.update(msg, &mut orders.proxy(Msg::PhaseSpread)),
+			Msg::Mean(msg) => self.mean.update(msg, &mut orders.proxy(Msg::Mean)),
+			Msg::Variance(msg) => self.variance.update(msg, &mut orders.proxy(Msg::Variance)),
+			Msg::Autocorrelation(msg) => self
+				.autocorrelation
+				.update(msg, &mut orders.proxy(Msg::Autocorrelation)),
+			Msg::Correlation(msg) => self
+				.correlation
+				.update(msg, &mut orders.proxy(Msg::Correlation)),
+			Msg::Path(msg) => self.path.update(msg, &mut orders.proxy(Msg::Path)),
+			Msg::Repeat(msg) => self.repeat.update(msg, &mut orders.proxy(Msg::Repeat)),
+		}
+	}
+
+	pub fn extract(&self) -> Option<Environment> {
+		match self.kind.value(true) {
+			Some(0) => Some(Environment::Random),
+			Some(1) => Some(Environment::AlternatingHalf),
+			Some(2) => Some(Environment::AlternatingThird),
+			Some(3) => Some(Environment::Sine {
+				period: self.period.value(true)?,
+				amplitude: self.amplitude.value(true)?,
+				phase_spread: self.phase_spread.value(true)?,
+				mean: self.mean.value(true)?,
+			}),
+			Some(4) => Some(Environment::RandomWalk),
+			Some(5) => Some(Environment::Constant),
+			Some(6) => Some(Environment::ConstantWithJumps),
+			Some(7) => Some(Environment::Correlated {
+				variance: self.variance.value(true)?,
+				autocorrelation: self.autocorrelation.value(true)?,
+				correlation: self.correlation.value(true)?,
+			}),
+			Some(8) => Some(Environment::FromSeries {
+				path: self.path.value(true)?,
+				repeat: self.repeat.value(true)?,
+			}),
+			Some(_) | None => None,
+		}
+	}
+
+	pub fn view(&self) -> Node<Msg> {
+		let kind = self.kind.value(false);
+		div![
+			self.kind.view(false).map_msg(Msg::Kind),
+			IF!(kind == Some(3) => div![
+				self.period.view(false).map_msg(Msg::Period),
+				self.amplitude.view(false).map_msg(Msg::Amplitude),
+				self.phase_spread.view(false).map_msg(Msg::PhaseSpread),
+				self.mean.view(false).map_msg(Msg::Mean),
+			]),
+			IF!(kind == Some(7) => div![
+				self.variance.view(false).map_msg(Msg::Variance),
+				self.autocorrelation.view(false).map_msg(Msg::Autocorrelation),
+				self.correlation.view(false).map_msg(Msg::Correlation),
+			]),
+			IF!(kind == Some(8) => div![
+				self.path.view(false).map_msg(Msg::Path),
+				self.repeat.view(false).map_msg(Msg::Repeat),
+			]),
+		]
+	}
+}
@@ -0,0 +1,62 @@
+use seed::{prelude::*, *};
+
+use crate::api::{make_suggestions, FitnessFunction};
+use crate::fields::{Field, InputField, SelectField};
+
+#[derive(Clone, Debug)]
+pub enum Msg {
+	Kind(<SelectField as Field>::Msg),
+	Width(<InputField<f64> as Field>::Msg),
+}
+
+pub struct FitnessForm {
+	kind: SelectField,
+	width: InputField<f64>,
+}
+
+impl FitnessForm {
+	pub fn new() -> Self {
+		let kind_suggestions = make_suggestions(&[
+			"Gaussian",
+			"Quadratic (truncated at zero)",
+			"Truncation",
+			"Plateau",
+		]);
+
+		Self {
+			kind: SelectField::new("Fitness function", kind_suggestions, false)
+				.with_initial(Some(0)),
+			width: InputField::new("Plateau width", false)
+				.with_initial(Some(0.1))
+				.with_validator(|&value| {
+					(value < 0.0).then(|| "Number cannot be negative.".to_string())
+				}),
+		}
+	}
+
+	pub fn update(&mut self, msg: Msg, orders: &mut impl Orders<Msg>) -> bool {
+		match msg {
+			Msg::Kind(msg) => self.kind.update(msg, &mut orders.proxy(Msg::Kind)),
+			Msg::Width(msg) => self.width.update(msg, &mut orders.proxy(Msg::Width)),
+		}
+	}
+
+	pub fn extract(&self) -> Option<FitnessFunction> {
+		match self.kind.value(true) {
+			Some(0) => Some(FitnessFunction::Gaussian),
+			Some(1) => Some(FitnessFunction::Quadratic),
+			Some(2) => Some(FitnessFunction::Truncation),
+			Some(3) => Some(FitnessFunction::Plateau {
+				width: self.width.value(true)?,
+			}),
+			Some(_) | None => None,
+		}
+	}
+
+	pub fn view(&self) -> Node<Msg> {
+		div![
+			self.kind.view(false).map_msg(Msg::Kind),
+			IF!(self.kind.value(false) == Some(3) => self.width.view(false).map_msg(Msg::Width)),
+		]
+	}
+}
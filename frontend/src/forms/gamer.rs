@@ -1,9 +1,19 @@
 use seed::{prelude::*, *};
 
-use crate::api::{Config, InitConfig, Suggestion, Suggestions, Environment, make_suggestions};
+use crate::api::{Config, InitConfig, Suggestion, Suggestions};
 use crate::components::Button;
 use crate::fields::slider::SliderField;
 use crate::fields::{Field, InputField, SelectField, CheckboxField};
+use crate::forms::dispersal::{self, DispersalForm};
+use crate::forms::environment::{self, EnvironmentForm};
+use crate::forms::fitness::{self, FitnessForm};
+use crate::forms::mutation::{self, MutationForm};
+use crate::forms::traits::{self, TraitsForm};
+use crate::forms::markers::{self, MarkersForm};
+use crate::forms::dioecy::{self, DioecyForm};
+use crate::forms::dynamics::{self, DynamicsForm};
+use crate::forms::seed_bank::{self, SeedBankForm};
+use crate::forms::plasticity::{self, PlasticityForm};
 use seed::futures::StreamExt;
 
 #[derive(Clone, Debug)]
@@ -11,50 +21,108 @@ pub enum Msg {
     MutationMu(<InputField<f64> as Field>::Msg),
     MutationSigma(<InputField<f64> as Field>::Msg),
     MutationStep(<InputField<f64> as Field>::Msg),
-    Environment(<SelectField as Field>::Msg),
+    MutatorSigma(<InputField<f64> as Field>::Msg),
+    Markers(markers::Msg),
+    Mutation(mutation::Msg),
+    Environment(environment::Msg),
     Rec(<InputField<f64> as Field>::Msg),
+    Selfing(<InputField<f64> as Field>::Msg),
+    AssortativeMating(<InputField<f64> as Field>::Msg),
     SelectionSigma(<InputField<f64> as Field>::Msg),
+    Fitness(fitness::Msg),
+    RMax(<InputField<f64> as Field>::Msg),
+    CompetitionSigma(<InputField<f64> as Field>::Msg),
+    Dioecy(dioecy::Msg),
+    DevelopmentalVariance(<InputField<f64> as Field>::Msg),
+    Plasticity(plasticity::Msg),
+    Traits(traits::Msg),
     Gamma(<InputField<f64> as Field>::Msg),
+    Survival(<InputField<String> as Field>::Msg),
     M(<InputField<f64> as Field>::Msg),
+    SeedBank(seed_bank::Msg),
+    DispersalSigma(<InputField<f64> as Field>::Msg),
+    HardSelection(<InputField<f64> as Field>::Msg),
+    Dynamics(dynamics::Msg),
+    Dispersal(dispersal::Msg),
 }
 
 pub struct GamerConfigForm {
     mutation_mu: InputField<f64>,
     mutation_sigma: InputField<f64>,
     mutation_step: InputField<f64>,
+    mutator_sigma: InputField<f64>,
+    markers: MarkersForm,
+    mutation: MutationForm,
     rec: InputField<f64>,
-    environment: SelectField,
+    selfing: InputField<f64>,
+    assortative_mating: InputField<f64>,
+    environment: EnvironmentForm,
     selection_sigma: InputField<f64>,
+    fitness: FitnessForm,
+    r_max: InputField<f64>,
+    competition_sigma: InputField<f64>,
+    dioecy: DioecyForm,
+    developmental_variance: InputField<f64>,
+    plasticity: PlasticityForm,
+    traits: TraitsForm,
     gamma: InputField<f64>,
+    survival: InputField<String>,
     m: InputField<f64>,
+    seed_bank: SeedBankForm,
+    dispersal_sigma: InputField<f64>,
+    hard_selection: InputField<f64>,
+    population_dynamics: DynamicsForm,
+    dispersal: DispersalForm,
 }
 
 impl GamerConfigForm {
     pub fn new() -> Self {
-        let kind_suggestions = make_suggestions(&[
-            "Random",
-            "Alternating with 50% chance",
-            "Alternating with 67% chance",
-            "Slow sinusoid with patch offset",
-            "Medium sinusoid with patch offset",
-            "Fast sinusoid with patch offset",
-            "Random walk",
-            "Constant",
-            "Constant with jumps"
-        ]);
-
         Self {
             mutation_mu: InputField::new("Mutation probability", false).with_initial(Some(0.01)),
             mutation_sigma: InputField::new("Mutational effect", false).with_initial(Some(0.01)),
             mutation_step: InputField::new("Mutational step size", false).with_initial(Some(0.01))
                 .with_validator(|&value| (value <= 0.0).then(|| "Number must be strictly positive.".to_string())),
+            mutator_sigma: InputField::new("Mutator mutational effect", true)
+                .with_placeholder("leave empty for a fixed mutation probability")
+                .with_validator(|&value| (value <= 0.0).then(|| "Number must be strictly positive.".to_string())),
+            markers: MarkersForm::new(),
+            mutation: MutationForm::new(),
             rec: InputField::new("Recombination probability", false).with_initial(Some(0.01)),
-            environment: SelectField::new("Environment function", kind_suggestions, false)
-                .with_initial(Some(1)),
+            selfing: InputField::new("Selfing rate", false).with_initial(Some(0.0))
+                .with_validator(|&value| (!(0.0..=1.0).contains(&value)).then(|| "Number must be between 0 and 1.".to_string())),
+            assortative_mating: InputField::new("Assortative mating strength", false).with_initial(Some(0.0))
+                .with_validator(|&value| (value < 0.0).then(|| "Number cannot be negative.".to_string())),
+            environment: EnvironmentForm::new(),
             selection_sigma: InputField::new("Selection strength", false).with_initial(Some(0.3))
                 .with_validator(|&value| (value <= 0.0).then(|| "Number must be strictly positive.".to_string())),
+            fitness: FitnessForm::new(),
+            traits: TraitsForm::new(),
+            r_max: InputField::new("Maximal reproductive success", false).with_initial(Some(1.0))
+                .with_validator(|&value| (value <= 0.0).then(|| "Number must be strictly positive.".to_string())),
+            competition_sigma: InputField::new("Competition width", true)
+                .with_placeholder("leave empty for no competition")
+                .with_validator(|&value| (value <= 0.0).then(|| "Number must be strictly positive.".to_string())),
+            dioecy: DioecyForm::new(),
+            developmental_variance: InputField::new("Developmental noise variance", false).with_initial(Some(0.0))
+                .with_validator(|&value| (value < 0.0).then(|| "Number cannot be negative.".to_string())),
+            plasticity: PlasticityForm::new(),
             gamma: InputField::new("Generation Overlap", false).with_initial(Some(0.0)),
+            survival: InputField::new("Survival probability per age", true)
+                .with_placeholder("separated by ',', leave empty to use the generation overlap for every age")
+                .with_validator(|value: &String| {
+                    mutation::parse_rates(value)
+                        .is_none()
+                        .then(|| "Probabilities must lie between 0 and 1.".to_string())
+                }),
             m: InputField::new("Dispersal probability", false).with_initial(Some(1.0)),
+            seed_bank: SeedBankForm::new(),
+            dispersal_sigma: InputField::new("Dispersal mutational effect", true)
+                .with_placeholder("leave empty for a fixed dispersal probability")
+                .with_validator(|&value| (value <= 0.0).then(|| "Number must be strictly positive.".to_string())),
+            hard_selection: InputField::new("Hard selection", false).with_initial(Some(0.0))
+                .with_validator(|&value| (!(0.0..=1.0).contains(&value)).then(|| "Number must be between 0 and 1.".to_string())),
+            population_dynamics: DynamicsForm::new(),
+            dispersal: DispersalForm::new(),
         }
     }
 
@@ -70,16 +138,52 @@ impl GamerConfigForm {
             Msg::MutationStep(msg) => self
                 .mutation_step
                 .update(msg, &mut orders.proxy(Msg::MutationStep)),
+            Msg::MutatorSigma(msg) => self
+                .mutator_sigma
+                .update(msg, &mut orders.proxy(Msg::MutatorSigma)),
+            Msg::Markers(msg) => self.markers.update(msg, &mut orders.proxy(Msg::Markers)),
+            Msg::Mutation(msg) => self
+                .mutation
+                .update(msg, &mut orders.proxy(Msg::Mutation)),
             Msg::Environment(msg) => self
                 .environment
                 .update(msg, &mut orders.proxy(Msg::Environment)),
             Msg::Rec(msg) => self.rec.update(msg, &mut orders.proxy(Msg::Rec)),
+            Msg::Selfing(msg) => self.selfing.update(msg, &mut orders.proxy(Msg::Selfing)),
+            Msg::AssortativeMating(msg) => self
+                .assortative_mating
+                .update(msg, &mut orders.proxy(Msg::AssortativeMating)),
             Msg::SelectionSigma(msg) => {
                 self.selection_sigma
                     .update(msg, &mut orders.proxy(Msg::SelectionSigma))
             }
+            Msg::Fitness(msg) => self
+                .fitness
+                .update(msg, &mut orders.proxy(Msg::Fitness)),
+            Msg::RMax(msg) => self.r_max.update(msg, &mut orders.proxy(Msg::RMax)),
+            Msg::CompetitionSigma(msg) => self
+                .competition_sigma
+                .update(msg, &mut orders.proxy(Msg::CompetitionSigma)),
+            Msg::Dioecy(msg) => self.dioecy.update(msg, &mut orders.proxy(Msg::Dioecy)),
+            Msg::DevelopmentalVariance(msg) => self
+                .developmental_variance
+                .update(msg, &mut orders.proxy(Msg::DevelopmentalVariance)),
+            Msg::Plasticity(msg) => self.plasticity.update(msg, &mut orders.proxy(Msg::Plasticity)),
+            Msg::Traits(msg) => self.traits.update(msg, &mut orders.proxy(Msg::Traits)),
             Msg::Gamma(msg) => self.gamma.update(msg, &mut orders.proxy(Msg::Gamma)),
+            Msg::Survival(msg) => self.survival.update(msg, &mut orders.proxy(Msg::Survival)),
             Msg::M(msg) => self.m.update(msg, &mut orders.proxy(Msg::M)),
+            Msg::SeedBank(msg) => self.seed_bank.update(msg, &mut orders.proxy(Msg::SeedBank)),
+            Msg::DispersalSigma(msg) => self
+                .dispersal_sigma
+                .update(msg, &mut orders.proxy(Msg::DispersalSigma)),
+            Msg::HardSelection(msg) => self
+                .hard_selection
+                .update(msg, &mut orders.proxy(Msg::HardSelection)),
+            Msg::Dynamics(msg) => self.population_dynamics.update(msg, &mut orders.proxy(Msg::Dynamics)),
+            Msg::Dispersal(msg) => self
+                .dispersal
+                .update(msg, &mut orders.proxy(Msg::Dispersal)),
         }
     }
 
@@ -87,52 +191,104 @@ impl GamerConfigForm {
         let mutation_mu = self.mutation_mu.value(true);
         let mutation_sigma = self.mutation_sigma.value(true);
         let mutation_step = self.mutation_step.value(true);
+        let mutator_sigma = self.mutator_sigma.value(true);
+        let markers = self.markers.extract()?;
+        let (mutation_model, mutation_rates) = self.mutation.extract()?;
         let rec = self.rec.value(true);
+        let selfing = self.selfing.value(true);
+        let assortative_mating = self.assortative_mating.value(true);
         let selection_sigma = self.selection_sigma.value(true);
+        let fitness = self.fitness.extract();
+        let r_max = self.r_max.value(true);
+        let competition_sigma = self.competition_sigma.value(true);
+        let dioecy = self.dioecy.extract()?;
+        let developmental_variance = self.developmental_variance.value(true);
+        let plasticity = self.plasticity.extract()?;
+        let (optima, selection_covariance) = self.traits.extract()?;
         let gamma = self.gamma.value(true);
-        let m = self.m.value(true);
-        let environment = self.environment.value(true);
-
-        let environment = match environment {
-            Some(0) => Environment::Random,
-            Some(1) => Environment::AlternatingHalf,
-            Some(2) => Environment::AlternatingThird,
-            Some(3) => Environment::SineSlow,
-            Some(4) => Environment::SineMedium,
-            Some(5) => Environment::SineFast,
-            Some(6) => Environment::RandomWalk,
-            Some(7) => Environment::Constant,
-            Some(8) => Environment::ConstantWithJumps,
-            Some(_) | None => return None,
+        let survival = match self.survival.value(true) {
+            Some(text) => Some(mutation::parse_rates(&text)?),
+            None => None,
         };
+        let m = self.m.value(true);
+        let seed_bank = self.seed_bank.extract()?;
+        let dispersal_sigma = self.dispersal_sigma.value(true);
+        let hard_selection = self.hard_selection.value(true);
+        let population_dynamics = self.population_dynamics.extract();
+        let dispersal = self.dispersal.extract();
+        let environment = self.environment.extract();
 
         Some(Config {
             mutation_mu: mutation_mu?,
             mutation_sigma: mutation_sigma?,
             mutation_step: mutation_step?,
+            mutator_sigma,
+            markers,
+            mutation_model,
+            mutation_rates,
             rec: rec?,
+            selfing: selfing?,
+            assortative_mating: assortative_mating?,
             selection_sigma: selection_sigma?,
+            fitness: fitness?,
+            r_max: r_max?,
+            competition_sigma,
+            dioecy,
+            developmental_variance: developmental_variance?,
+            plasticity,
+            optima,
+            selection_covariance,
             gamma: gamma?,
+            survival,
             m: m?,
-            environment
+            seed_bank,
+            dispersal_sigma,
+            hard_selection: hard_selection?,
+            population_dynamics: population_dynamics?,
+            dispersal: dispersal?,
+            environment: environment?
         })
     }
 
     pub fn view(&self) -> Node<Msg> {
         div![
             self.rec.view(false).map_msg(Msg::Rec),
+            self.selfing.view(false).map_msg(Msg::Selfing),
+            self.assortative_mating.view(false).map_msg(Msg::AssortativeMating),
             hr![],
-			self.environment.view(false).map_msg(Msg::Environment),
+			self.environment.view().map_msg(Msg::Environment),
 			hr![],
 			self.selection_sigma
 				.view(false)
 				.map_msg(Msg::SelectionSigma),
+			self.fitness.view().map_msg(Msg::Fitness),
+			self.r_max.view(false).map_msg(Msg::RMax),
+			self.competition_sigma
+				.view(false)
+				.map_msg(Msg::CompetitionSigma),
+			self.traits.view().map_msg(Msg::Traits),
+			self.plasticity.view().map_msg(Msg::Plasticity),
+			self.dioecy.view().map_msg(Msg::Dioecy),
+			self.developmental_variance.view(false).map_msg(Msg::DevelopmentalVariance),
 			self.gamma.view(false).map_msg(Msg::Gamma),
+			self.survival.view(false).map_msg(Msg::Survival),
+			self.seed_bank.view().map_msg(Msg::SeedBank),
 			self.m.view(false).map_msg(Msg::M),
+			self.dispersal_sigma
+				.view(false)
+				.map_msg(Msg::DispersalSigma),
+			self.hard_selection.view(false).map_msg(Msg::HardSelection),
+			self.population_dynamics.view().map_msg(Msg::Dynamics),
+			self.dispersal.view().map_msg(Msg::Dispersal),
 			hr![],
 			self.mutation_mu.view(false).map_msg(Msg::MutationMu),
 			self.mutation_sigma.view(false).map_msg(Msg::MutationSigma),
 			self.mutation_step.view(false).map_msg(Msg::MutationStep),
+			self.mutation.view().map_msg(Msg::Mutation),
+			self.mutator_sigma
+				.view(false)
+				.map_msg(Msg::MutatorSigma),
+			self.markers.view().map_msg(Msg::Markers),
 		]
     }
 }
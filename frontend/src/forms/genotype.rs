@@ -0,0 +1,61 @@
+use seed::{prelude::*, *};
+
+use crate::api::{make_suggestions, GenotypeMap};
+use crate::fields::{Field, InputField, SelectField};
+
+#[derive(Clone, Debug)]
+pub enum Msg {
+	Kind(<SelectField as Field>::Msg),
+	Dominance(<InputField<f64> as Field>::Msg),
+	Epistasis(<InputField<f64> as Field>::Msg),
+}
+
+pub struct GenotypeMapForm {
+	kind: SelectField,
+	dominance: InputField<f64>,
+	epistasis: InputField<f64>,
+}
+
+impl GenotypeMapForm {
+	pub fn new() -> Self {
+		let kind_suggestions = make_suggestions(&["Additive", "Dominance", "Pairwise epistasis"]);
+
+		Self {
+			kind: SelectField::new("Genotype-phenotype map", kind_suggestions, false)
+				.with_initial(Some(0)),
+			dominance: InputField::new("Dominance of the larger allele (h)", false)
+				.with_initial(Some(0.5)),
+			epistasis: InputField::new("Epistatic coefficient", false).with_initial(Some(0.0)),
+		}
+	}
+
+	pub fn update(&mut self, msg: Msg, orders: &mut impl Orders<Msg>) -> bool {
+		match msg {
+			Msg::Kind(msg) => self.kind.update(msg, &mut orders.proxy(Msg::Kind)),
+			Msg::Dominance(msg) => self.dominance.update(msg, &mut orders.proxy(Msg::Dominance)),
+			Msg::Epistasis(msg) => self.epistasis.update(msg, &mut orders.proxy(Msg::Epistasis)),
+		}
+	}
+
+	pub fn extract(&self) -> Option<GenotypeMap> {
+		match self.kind.value(true) {
+			Some(0) => Some(GenotypeMap::Additive),
+			Some(1) => Some(GenotypeMap::Dominance {
+				h: self.dominance.value(true)?,
+			}),
+			Some(2) => Some(GenotypeMap::Epistasis {
+				epsilon: self.epistasis.value(true)?,
+			}),
+			Some(_) | None => None,
+		}
+	}
+
+	pub fn view(&self, disabled: bool) -> Node<Msg> {
+		let kind = self.kind.value(false);
+		div![
+			self.kind.view(disabled).map_msg(Msg::Kind),
+			IF!(kind == Some(1) => self.dominance.view(disabled).map_msg(Msg::Dominance)),
+			IF!(kind == Some(2) => self.epistasis.view(disabled).map_msg(Msg::Epistasis)),
+		]
+	}
+}
@@ -5,6 +5,8 @@ use crate::{
 	api::{Config, InitConfig, InitialPopulation, Suggestion, Suggestions},
 	components::Button,
 	fields::{slider::SliderField, Field, InputField, SelectField},
+	forms::capacities::{self, CapacitiesForm},
+	forms::genotype::{self, GenotypeMapForm},
 };
 
 #[derive(Clone, Debug)]
@@ -14,7 +16,12 @@ pub enum Msg {
 	Individuals(<InputField<u64> as Field>::Msg),
 	Patches(<InputField<u64> as Field>::Msg),
 	Loci(<InputField<u64> as Field>::Msg),
+	Traits(<InputField<u64> as Field>::Msg),
+	Capacities(capacities::Msg),
 	Diploid(<InputField<bool> as Field>::Msg),
+	GenotypeMap(genotype::Msg),
+	RecombinationMap(<InputField<String> as Field>::Msg),
+	Seed(<InputField<u64> as Field>::Msg),
 }
 
 pub struct InitConfigForm {
@@ -23,7 +30,20 @@ pub struct InitConfigForm {
 	individuals: InputField<u64>,
 	patches: InputField<u64>,
 	loci: InputField<u64>,
+	traits: InputField<u64>,
+	capacities: CapacitiesForm,
 	diploid: InputField<bool>,
+	genotype_map: GenotypeMapForm,
+	recombination_map: InputField<String>,
+	seed: InputField<u64>,
+}
+
+/// parse crossover probabilities separated by ',', which must lie between 0 and 0.5
+fn parse_recombination_map(text: &str) -> Option<Vec<f64>> {
+	text.split(',')
+		.map(|x| x.trim().parse::<f64>().ok())
+		.map(|x| x.filter(|x| (0.0..=0.5).contains(x)))
+		.collect()
 }
 
 impl InitConfigForm {
@@ -62,7 +82,22 @@ impl InitConfigForm {
 				.with_validator(|&value| {
 					(value == 0).then(|| "Number must be strictly positive.".to_string())
 				}),
+			traits: InputField::new("Trait amount", false)
+				.with_initial(Some(1))
+				.with_validator(|&value| {
+					(value == 0).then(|| "Number must be strictly positive.".to_string())
+				}),
+			capacities: CapacitiesForm::new(),
 			diploid: InputField::new("Diploid", false).with_initial(Some(false)),
+			genotype_map: GenotypeMapForm::new(),
+			recombination_map: InputField::new("Recombination map", true)
+				.with_placeholder("crossover probability between adjacent loci, separated by ','")
+				.with_validator(|value: &String| {
+					parse_recombination_map(value)
+						.is_none()
+						.then(|| "Probabilities must lie between 0 and 0.5.".to_string())
+				}),
+			seed: InputField::new("Seed", true).with_placeholder("leave empty for a random seed"),
 		}
 	}
 
@@ -76,7 +111,18 @@ impl InitConfigForm {
 				.update(msg, &mut orders.proxy(Msg::Individuals)),
 			Msg::Patches(msg) => self.patches.update(msg, &mut orders.proxy(Msg::Patches)),
 			Msg::Loci(msg) => self.loci.update(msg, &mut orders.proxy(Msg::Loci)),
+			Msg::Traits(msg) => self.traits.update(msg, &mut orders.proxy(Msg::Traits)),
+			Msg::Capacities(msg) => self
+				.capacities
+				.update(msg, &mut orders.proxy(Msg::Capacities)),
 			Msg::Diploid(msg) => self.diploid.update(msg, &mut orders.proxy(Msg::Diploid)),
+			Msg::GenotypeMap(msg) => self
+				.genotype_map
+				.update(msg, &mut orders.proxy(Msg::GenotypeMap)),
+			Msg::RecombinationMap(msg) => self
+				.recombination_map
+				.update(msg, &mut orders.proxy(Msg::RecombinationMap)),
+			Msg::Seed(msg) => self.seed.update(msg, &mut orders.proxy(Msg::Seed)),
 		}
 	}
 
@@ -86,7 +132,15 @@ impl InitConfigForm {
 		let individuals = self.individuals.value(true);
 		let patches = self.patches.value(true);
 		let loci = self.loci.value(true);
+		let traits = self.traits.value(true);
+		let capacities = self.capacities.extract();
 		let diploid = self.diploid.value(true);
+		let genotype_map = self.genotype_map.extract();
+		let recombination_map = match self.recombination_map.value(true) {
+			Some(text) => Some(parse_recombination_map(&text)?),
+			None => None,
+		};
+		let seed = self.seed.value(true);
 
 		let kind = match kind {
 			Some(0) => InitialPopulation::UniformI,
@@ -109,7 +163,12 @@ impl InitConfigForm {
 			individuals: individuals? as usize,
 			patches: patches? as usize,
 			loci: loci? as usize,
+			traits: traits? as usize,
 			diploid: diploid?,
+			genotype_map: genotype_map?,
+			recombination_map,
+			capacities: capacities?,
+			seed,
 		})
 	}
 
@@ -120,8 +179,15 @@ impl InitConfigForm {
 			self.individuals.view(disabled).map_msg(Msg::Individuals),
 			self.patches.view(disabled).map_msg(Msg::Patches),
 			self.loci.view(disabled).map_msg(Msg::Loci),
+			self.traits.view(disabled).map_msg(Msg::Traits),
+			self.capacities.view(disabled).map_msg(Msg::Capacities),
 			hr![],
 			self.diploid.view(disabled).map_msg(Msg::Diploid),
+			self.genotype_map.view(disabled).map_msg(Msg::GenotypeMap),
+			self.recombination_map
+				.view(disabled)
+				.map_msg(Msg::RecombinationMap),
+			self.seed.view(disabled).map_msg(Msg::Seed),
 		]
 	}
 }
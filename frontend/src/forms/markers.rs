@@ -0,0 +1,71 @@
+use seed::{prelude::*, *};
+
+use crate::api::{make_suggestions, MarkerModel, Markers};
+use crate::fields::{Field, InputField, SelectField};
+
+#[derive(Clone, Debug)]
+pub enum Msg {
+	Loci(<InputField<u64> as Field>::Msg),
+	Mu(<InputField<f64> as Field>::Msg),
+	Model(<SelectField as Field>::Msg),
+}
+
+pub struct MarkersForm {
+	loci: InputField<u64>,
+	mu: InputField<f64>,
+	model: SelectField,
+}
+
+impl MarkersForm {
+	pub fn new() -> Self {
+		let model_suggestions = make_suggestions(&["Infinite alleles", "Microsatellite (stepwise)"]);
+
+		Self {
+			loci: InputField::new("Neutral marker loci", true)
+				.with_placeholder("leave empty for no markers")
+				.with_validator(|&value| (value == 0).then(|| "Number must be strictly positive.".to_string())),
+			mu: InputField::new("Marker mutation probability", false)
+				.with_initial(Some(0.001))
+				.with_validator(|&value| {
+					(!(0.0..=1.0).contains(&value)).then(|| "Number must be between 0 and 1.".to_string())
+				}),
+			model: SelectField::new("Marker mutation model", model_suggestions, false)
+				.with_initial(Some(0)),
+		}
+	}
+
+	pub fn update(&mut self, msg: Msg, orders: &mut impl Orders<Msg>) -> bool {
+		match msg {
+			Msg::Loci(msg) => self.loci.update(msg, &mut orders.proxy(Msg::Loci)),
+			Msg::Mu(msg) => self.mu.update(msg, &mut orders.proxy(Msg::Mu)),
+			Msg::Model(msg) => self.model.update(msg, &mut orders.proxy(Msg::Model)),
+		}
+	}
+
+	/// the neutral markers, None if the form is invalid and Some(None) without markers
+	pub fn extract(&self) -> Option<Option<Markers>> {
+		let loci = match self.loci.value(true) {
+			Some(loci) => loci as usize,
+			None => return Some(None),
+		};
+		let model = match self.model.value(true)? {
+			0 => MarkerModel::InfiniteAlleles,
+			_ => MarkerModel::Stepwise,
+		};
+		Some(Some(Markers {
+			loci,
+			mu: self.mu.value(true)?,
+			model,
+		}))
+	}
+
+	pub fn view(&self) -> Node<Msg> {
+		div![
+			self.loci.view(false).map_msg(Msg::Loci),
+			IF!(self.loci.value(false).is_some() => div![
+				self.mu.view(false).map_msg(Msg::Mu),
+				self.model.view(false).map_msg(Msg::Model),
+			]),
+		]
+	}
+}
@@ -3,6 +3,18 @@ pub mod init;
 pub mod simulation;
 pub mod gamer;
 pub mod forget;
+pub mod dioecy;
+pub mod dispersal;
+pub mod dynamics;
+pub mod environment;
+pub mod fitness;
+pub mod capacities;
+pub mod genotype;
+pub mod markers;
+pub mod mutation;
+pub mod plasticity;
+pub mod seed_bank;
+pub mod traits;
 
 pub use config::*;
 pub use init::*;
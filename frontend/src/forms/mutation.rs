@@ -0,0 +1,93 @@
+use seed::{prelude::*, *};
+
+use crate::api::{make_suggestions, MutationModel};
+use crate::fields::{Field, InputField, SelectField};
+
+#[derive(Clone, Debug)]
+pub enum Msg {
+	Kind(<SelectField as Field>::Msg),
+	Min(<InputField<f64> as Field>::Msg),
+	Max(<InputField<f64> as Field>::Msg),
+	Rates(<InputField<String> as Field>::Msg),
+}
+
+pub struct MutationForm {
+	kind: SelectField,
+	min: InputField<f64>,
+	max: InputField<f64>,
+	rates: InputField<String>,
+}
+
+/// parse mutation probabilities separated by ',', which must lie between 0 and 1
+pub fn parse_rates(text: &str) -> Option<Vec<f64>> {
+	text.split(',')
+		.map(|x| x.trim().parse::<f64>().ok())
+		.map(|x| x.filter(|x| (0.0..=1.0).contains(x)))
+		.collect()
+}
+
+impl MutationForm {
+	pub fn new() -> Self {
+		let kind_suggestions = make_suggestions(&[
+			"Gaussian",
+			"Discretised gaussian",
+			"Stepwise",
+			"House of cards",
+			"Bounded with reflecting boundaries",
+		]);
+
+		Self {
+			kind: SelectField::new("Mutation model", kind_suggestions, false)
+				.with_initial(Some(1)),
+			min: InputField::new("Lower bound", false).with_initial(Some(-1.0)),
+			max: InputField::new("Upper bound", false).with_initial(Some(1.0)),
+			rates: InputField::new("Mutation probability per locus", true)
+				.with_placeholder("separated by ',', leave empty to use the same for every locus")
+				.with_validator(|value: &String| {
+					parse_rates(value)
+						.is_none()
+						.then(|| "Probabilities must lie between 0 and 1.".to_string())
+				}),
+		}
+	}
+
+	pub fn update(&mut self, msg: Msg, orders: &mut impl Orders<Msg>) -> bool {
+		match msg {
+			Msg::Kind(msg) => self.kind.update(msg, &mut orders.proxy(Msg::Kind)),
+			Msg::Min(msg) => self.min.update(msg, &mut orders.proxy(Msg::Min)),
+			Msg::Max(msg) => self.max.update(msg, &mut orders.proxy(Msg::Max)),
+			Msg::Rates(msg) => self.rates.update(msg, &mut orders.proxy(Msg::Rates)),
+		}
+	}
+
+	/// the mutation model and the per locus mutation probabilities
+	pub fn extract(&self) -> Option<(MutationModel, Option<Vec<f64>>)> {
+		let model = match self.kind.value(true) {
+			Some(0) => MutationModel::Gaussian,
+			Some(1) => MutationModel::DiscreteGaussian,
+			Some(2) => MutationModel::Stepwise,
+			Some(3) => MutationModel::HouseOfCards,
+			Some(4) => MutationModel::Bounded {
+				min: self.min.value(true)?,
+				max: self.max.value(true)?,
+			},
+			Some(_) | None => return None,
+		};
+		let rates = match self.rates.value(true) {
+			Some(text) => Some(parse_rates(&text)?),
+			None => None,
+		};
+		Some((model, rates))
+	}
+
+	pub fn view(&self) -> Node<Msg> {
+		div![
+			self.kind.view(false).map_msg(Msg::Kind),
+			IF!(self.kind.value(false) == Some(4) => div![
+				self.min.view(false).map_msg(Msg::Min),
+				self.max.view(false).map_msg(Msg::Max),
+			]),
+			self.rates.view(false).map_msg(Msg::Rates),
+		]
+	}
+}
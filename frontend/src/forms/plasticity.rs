@@ -0,0 +1,70 @@
+use seed::{prelude::*, *};
+
+use crate::api::Plasticity;
+use crate::fields::{Field, InputField};
+
+#[derive(Clone, Debug)]
+pub enum Msg {
+	Sigma(<InputField<f64> as Field>::Msg),
+	CueNoise(<InputField<f64> as Field>::Msg),
+	CueLag(<InputField<u64> as Field>::Msg),
+	Cost(<InputField<f64> as Field>::Msg),
+}
+
+pub struct PlasticityForm {
+	sigma: InputField<f64>,
+	cue_noise: InputField<f64>,
+	cue_lag: InputField<u64>,
+	cost: InputField<f64>,
+}
+
+impl PlasticityForm {
+	pub fn new() -> Self {
+		Self {
+			sigma: InputField::new("Reaction norm mutational effect", true)
+				.with_placeholder("leave empty for no plasticity")
+				.with_validator(|&value| (value <= 0.0).then(|| "Number must be strictly positive.".to_string())),
+			cue_noise: InputField::new("Noise on the environmental cue", false)
+				.with_initial(Some(0.0))
+				.with_validator(|&value| (value < 0.0).then(|| "Number cannot be negative.".to_string())),
+			cue_lag: InputField::new("Lag of the environmental cue", false).with_initial(Some(0)),
+			cost: InputField::new("Cost of plasticity", false)
+				.with_initial(Some(0.0))
+				.with_validator(|&value| (value < 0.0).then(|| "Number cannot be negative.".to_string())),
+		}
+	}
+
+	pub fn update(&mut self, msg: Msg, orders: &mut impl Orders<Msg>) -> bool {
+		match msg {
+			Msg::Sigma(msg) => self.sigma.update(msg, &mut orders.proxy(Msg::Sigma)),
+			Msg::CueNoise(msg) => self.cue_noise.update(msg, &mut orders.proxy(Msg::CueNoise)),
+			Msg::CueLag(msg) => self.cue_lag.update(msg, &mut orders.proxy(Msg::CueLag)),
+			Msg::Cost(msg) => self.cost.update(msg, &mut orders.proxy(Msg::Cost)),
+		}
+	}
+
+	/// the reaction norm, None if the form is invalid and Some(None) without plasticity
+	pub fn extract(&self) -> Option<Option<Plasticity>> {
+		let sigma = match self.sigma.value(true) {
+			Some(sigma) => sigma,
+			None => return Some(None),
+		};
+		Some(Some(Plasticity {
+			sigma,
+			cue_noise: self.cue_noise.value(true)?,
+			cue_lag: self.cue_lag.value(true)? as usize,
+			cost: self.cost.value(true)?,
+		}))
+	}
+
+	pub fn view(&self) -> Node<Msg> {
+		div![
+			self.sigma.view(false).map_msg(Msg::Sigma),
+			IF!(self.sigma.value(false).is_some() => div![
+				self.cue_noise.view(false).map_msg(Msg::CueNoise),
+				self.cue_lag.view(false).map_msg(Msg::CueLag),
+				self.cost.view(false).map_msg(Msg::Cost),
+			]),
+		]
+	}
+}
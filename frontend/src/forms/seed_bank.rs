@@ -0,0 +1,70 @@
+use seed::{prelude::*, *};
+
+use crate::api::SeedBank;
+use crate::fields::{Field, InputField};
+
+#[derive(Clone, Debug)]
+pub enum Msg {
+	Dormancy(<InputField<f64> as Field>::Msg),
+	Germination(<InputField<f64> as Field>::Msg),
+	Decay(<InputField<f64> as Field>::Msg),
+}
+
+pub struct SeedBankForm {
+	dormancy: InputField<f64>,
+	germination: InputField<f64>,
+	decay: InputField<f64>,
+}
+
+fn probability(value: &f64) -> Option<String> {
+	(!(0.0..=1.0).contains(value)).then(|| "Number must be between 0 and 1.".to_string())
+}
+
+impl SeedBankForm {
+	pub fn new() -> Self {
+		Self {
+			dormancy: InputField::new("Dormancy probability", true)
+				.with_placeholder("leave empty for no seed bank")
+				.with_validator(probability),
+			germination: InputField::new("Germination probability", false)
+				.with_initial(Some(0.5))
+				.with_validator(probability),
+			decay: InputField::new("Seed bank decay probability", false)
+				.with_initial(Some(0.1))
+				.with_validator(probability),
+		}
+	}
+
+	pub fn update(&mut self, msg: Msg, orders: &mut impl Orders<Msg>) -> bool {
+		match msg {
+			Msg::Dormancy(msg) => self.dormancy.update(msg, &mut orders.proxy(Msg::Dormancy)),
+			Msg::Germination(msg) => self
+				.germination
+				.update(msg, &mut orders.proxy(Msg::Germination)),
+			Msg::Decay(msg) => self.decay.update(msg, &mut orders.proxy(Msg::Decay)),
+		}
+	}
+
+	/// the seed bank, None if the form is invalid and Some(None) without a seed bank
+	pub fn extract(&self) -> Option<Option<SeedBank>> {
+		let dormancy = match self.dormancy.value(true) {
+			Some(dormancy) => dormancy,
+			None => return Some(None),
+		};
+		Some(Some(SeedBank {
+			dormancy,
+			germination: self.germination.value(true)?,
+			decay: self.decay.value(true)?,
+		}))
+	}
+
+	pub fn view(&self) -> Node<Msg> {
+		div![
+			self.dormancy.view(false).map_msg(Msg::Dormancy),
+			IF!(self.dormancy.value(false).is_some() => div![
+				self.germination.view(false).map_msg(Msg::Germination),
+				self.decay.view(false).map_msg(Msg::Decay),
+			]),
+		]
+	}
+}
@@ -0,0 +1,79 @@
+use seed::{prelude::*, *};
+
+use crate::fields::{Field, InputField};
+use crate::forms::dispersal::parse_matrix;
+
+#[derive(Clone, Debug)]
+pub enum Msg {
+	Optima(<InputField<String> as Field>::Msg),
+	Covariance(<InputField<String> as Field>::Msg),
+}
+
+pub struct TraitsForm {
+	optima: InputField<String>,
+	covariance: InputField<String>,
+}
+
+/// the offset and slope of every trait optimum and the selection covariance matrix
+type Selection = (Vec<(f64, f64)>, Option<Vec<Vec<f64>>>);
+
+/// parse the optima written as 'offset,slope' pairs separated by ';'
+fn parse_optima(text: &str) -> Option<Vec<(f64, f64)>> {
+	parse_matrix(text)?
+		.into_iter()
+		.map(|row| match row.as_slice() {
+			[offset, slope] => Some((*offset, *slope)),
+			_ => None,
+		})
+		.collect()
+}
+
+impl TraitsForm {
+	pub fn new() -> Self {
+		Self {
+			optima: InputField::new("Trait optima", true)
+				.with_placeholder("offset,slope per trait separated by ';'")
+				.with_validator(|value: &String| {
+					parse_optima(value)
+						.is_none()
+						.then(|| "Every trait needs an offset and a slope.".to_string())
+				}),
+			covariance: InputField::new("Selection covariance matrix", true)
+				.with_placeholder("rows separated by ';', leave empty to use the selection strength")
+				.with_validator(|value: &String| {
+					parse_matrix(value)
+						.is_none()
+						.then(|| "Matrix must only contain numbers.".to_string())
+				}),
+		}
+	}
+
+	pub fn update(&mut self, msg: Msg, orders: &mut impl Orders<Msg>) -> bool {
+		match msg {
+			Msg::Optima(msg) => self.optima.update(msg, &mut orders.proxy(Msg::Optima)),
+			Msg::Covariance(msg) => self
+				.covariance
+				.update(msg, &mut orders.proxy(Msg::Covariance)),
+		}
+	}
+
+	/// the trait optima and the selection covariance matrix
+	pub fn extract(&self) -> Option<Selection> {
+		let optima = match self.optima.value(true) {
+			Some(text) => parse_optima(&text)?,
+			None => vec![],
+		};
+		let covariance = match self.covariance.value(true) {
+			Some(text) => Some(parse_matrix(&text)?),
+			None => None,
+		};
+		Some((optima, covariance))
+	}
+
+	pub fn view(&self) -> Node<Msg> {
+		div![
+			self.optima.view(false).map_msg(Msg::Optima),
+			self.covariance.view(false).map_msg(Msg::Covariance),
+		]
+	}
+}
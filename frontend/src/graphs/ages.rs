@@ -0,0 +1,71 @@
+use crate::graphs::constants::*;
+use crate::GraphData;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use plotters_canvas::CanvasBackend;
+
+pub fn draw(
+	backend: &mut DrawingArea<CanvasBackend, Shift>,
+	history: &[(u64, GraphData)],
+	title: &str,
+	forget: bool,
+) -> Option<()> {
+	let font: FontDesc = ("sans-serif", 20.0).into();
+
+	let last = history.last()?.0;
+	let index = history
+		.iter()
+		.enumerate()
+		.find(|(_, (tick, _))| tick + MAX_HISTORY > last)
+		.map(|x| x.0);
+
+	let skip = match (forget, index) {
+		(true, Some(index)) => index,
+		_ => 0,
+	};
+
+	let x_range = history.get(skip)?.0..history.last()?.0;
+
+	let mut chart = ChartBuilder::on(&backend)
+		.margin(20)
+		.caption(title, font)
+		.x_label_area_size(30)
+		.y_label_area_size(30)
+		.build_cartesian_2d(x_range, 0.0..1.0)
+		.ok()?;
+
+	chart
+		.configure_mesh()
+		.disable_x_mesh()
+		.disable_y_mesh()
+		.x_labels(10)
+		.y_labels(5)
+		.draw()
+		.ok()?;
+
+	let step = match forget {
+		true => ((history.len() as u64).min(MAX_HISTORY) / MAX_COLS) + 1,
+		false => (history.len() as u64 / MAX_COLS) + 1,
+	} as usize;
+
+	let classes = history
+		.iter()
+		.map(|(_, data)| data.age_distribution.len())
+		.max()?;
+
+	// one line per age class, with the fraction of the population of that age
+	for class in 0..classes {
+		chart
+			.draw_series(LineSeries::new(
+				history
+					.iter()
+					.skip(skip)
+					.step_by(step)
+					.map(|(tick, data)| (*tick, data.age_distribution.get(class).copied().unwrap_or(0.0))),
+				&COLORS[class % COLORS.len()],
+			))
+			.ok()?;
+	}
+
+	Some(())
+}
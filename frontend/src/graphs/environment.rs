@@ -10,6 +10,7 @@ use std::ops::Range;
 pub fn draw(
 	backend: &mut DrawingArea<CanvasBackend, Shift>,
 	history: &[(u64, GraphData)],
+	map: impl Fn(&GraphData) -> &[f64],
 	y_range: Range<f64>,
 	title: &str,
 	forget: bool,
@@ -59,7 +60,7 @@ pub fn draw(
 		.skip(skip)
 		.step_by(step)
 		.map(|(t, d)| {
-			d.environment
+			map(d)
 				.iter()
 				.enumerate()
 				.map(move |(i, s)| (t, i, s))
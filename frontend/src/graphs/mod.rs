@@ -4,4 +4,5 @@ pub mod pheno;
 pub mod scheduler;
 pub mod environment;
 pub mod loci;
+pub mod ages;
 mod ordhelp;
@@ -6,7 +6,7 @@ use std::time::Duration;
 use wasm_timer::Instant;
 
 use super::ordhelp::*;
-use crate::graphs::{environment, line, loci, pheno};
+use crate::graphs::{ages, environment, line, loci, pheno};
 use plotters::coord::Shift;
 use plotters::prelude::*;
 use seed::{prelude::*, *};
@@ -15,6 +15,8 @@ use seed::{prelude::*, *};
 pub enum Tab {
 	General,
 	Loci,
+	Ages,
+	Differentiation,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -24,6 +26,17 @@ pub struct GraphData {
 	pub phenotype_sample: Vec<(usize, f64)>, // (patch_index, phenotype)
 	pub environment: Vec<f64>,
 	pub loci: Vec<Vec<(usize, f64)>>,
+	pub heritability: f64,
+	pub generation_time: f64,
+	pub age_distribution: Vec<f64>, // fraction per age, the last class holds all older ages
+	pub dormant: f64, // size of the seed bank relative to the population
+	pub occupancy: Vec<f64>, // size of every patch relative to its carrying capacity
+	pub fst: f64,
+	pub qst: f64,
+	pub mean_fitness: f64,
+	pub heterozygosity: f64, // expected heterozygosity on the mutation step grid
+	pub morphs: Vec<(f64, f64)>, // (mean, frequency) of every morph
+	pub branching: Option<u64>, // tick at which several morphs were first detected
 }
 
 #[derive(Clone, Debug, Default)]
@@ -33,6 +46,15 @@ pub struct GraphRanges {
 	phenotype_sample: Range<f64>,
 	environment: Range<f64>,
 	loci: Vec<Range<f64>>,
+	heritability: Range<f64>,
+	generation_time: Range<f64>,
+	dormant: Range<f64>,
+	occupancy: Range<f64>,
+	fst: Range<f64>,
+	qst: Range<f64>,
+	mean_fitness: Range<f64>,
+	heterozygosity: Range<f64>,
+	morphs: Range<f64>,
 }
 
 pub struct DrawScheduler {
@@ -95,15 +117,9 @@ impl DrawScheduler {
 	}
 
 	fn view_tabs(&self) -> Node<Tab> {
-		// FIXME: this is terrible
-		let which = match self.tab {
-			Tab::General => true,
-			Tab::Loci => false,
-		};
-
 		ul![
 			li![
-				IF!(which => C!["is-active"]),
+				IF!(matches!(self.tab, Tab::General) => C!["is-active"]),
 				a![
 					mouse_ev(Ev::Click, |_| Tab::General),
 					span![C!["icon is-small"], i![C!["fas fa-list"]]],
@@ -111,13 +127,29 @@ impl DrawScheduler {
 				]
 			],
 			li![
-				IF!(!which => C!["is-active"]),
+				IF!(matches!(self.tab, Tab::Loci) => C!["is-active"]),
 				a![
 					mouse_ev(Ev::Click, |_| Tab::Loci),
 					span![C!["icon is-small"], i![C!["fas fa-microscope"]]],
 					span!["Loci"]
 				]
 			],
+			li![
+				IF!(matches!(self.tab, Tab::Ages) => C!["is-active"]),
+				a![
+					mouse_ev(Ev::Click, |_| Tab::Ages),
+					span![C!["icon is-small"], i![C!["fas fa-hourglass-half"]]],
+					span!["Life cycle"]
+				]
+			],
+			li![
+				IF!(matches!(self.tab, Tab::Differentiation) => C!["is-active"]),
+				a![
+					mouse_ev(Ev::Click, |_| Tab::Differentiation),
+					span![C!["icon is-small"], i![C!["fas fa-code-branch"]]],
+					span!["Differentiation"]
+				]
+			],
 		]
 	}
 
@@ -146,6 +178,14 @@ impl DrawScheduler {
 	fn update_ranges(&mut self, data: &GraphData) {
 		range_assign(&mut self.ranges.phenotype_variance, data.phenotype_variance);
 		range_assign(&mut self.ranges.phenotype_distance, data.phenotype_distance);
+		range_assign(&mut self.ranges.heritability, data.heritability);
+		range_assign(&mut self.ranges.generation_time, data.generation_time);
+		range_assign(&mut self.ranges.dormant, data.dormant);
+		range_assign(&mut self.ranges.fst, data.fst);
+		range_assign(&mut self.ranges.qst, data.qst);
+		range_assign(&mut self.ranges.mean_fitness, data.mean_fitness);
+		range_assign(&mut self.ranges.heterozygosity, data.heterozygosity);
+		range_assign(&mut self.ranges.morphs, data.morphs.len() as f64);
 		range_slice_assign(
 			&mut self.ranges.phenotype_sample,
 			data.phenotype_sample.iter().map(|x| x.1),
@@ -154,6 +194,10 @@ impl DrawScheduler {
 			&mut self.ranges.environment,
 			data.environment.iter().cloned(),
 		);
+		range_slice_assign(
+			&mut self.ranges.occupancy,
+			data.occupancy.iter().cloned(),
+		);
 		self.ranges.loci.resize(data.loci.len(), Range::default());
 
 		for index in 0..data.loci.len() {
@@ -193,7 +237,7 @@ impl DrawScheduler {
 		&self,
 		root: &mut DrawingArea<CanvasBackend, Shift>,
 	) -> Result<(), &'static str> {
-		let mut rows = root.split_evenly((4, 1));
+		let mut rows = root.split_evenly((6, 1));
 		pheno::draw(
 			&mut rows[0],
 			&self.history,
@@ -206,8 +250,9 @@ impl DrawScheduler {
 		environment::draw(
 			&mut rows[1],
 			&self.history,
+			|data| &data.environment,
 			self.ranges.environment.clone(),
-			"environment per patch",
+			"environment of the first trait per patch",
 			self.forget
 		)
 			.ok_or("could not draw environment plot")?;
@@ -232,6 +277,26 @@ impl DrawScheduler {
 		)
 		.ok_or("could not draw phenotype plot")?;
 
+		line::draw(
+			&mut rows[4],
+			&self.history,
+			|data| data.heritability,
+			self.ranges.heritability.clone(),
+			"heritability",
+			self.forget
+		)
+		.ok_or("could not draw heritability plot")?;
+
+		line::draw(
+			&mut rows[5],
+			&self.history,
+			|data| data.mean_fitness,
+			self.ranges.mean_fitness.clone(),
+			"mean fitness",
+			self.forget
+		)
+		.ok_or("could not draw mean fitness plot")?;
+
 		Ok(())
 	}
 
@@ -263,6 +328,100 @@ impl DrawScheduler {
 		Ok(())
 	}
 
+	fn draw_ages_tab(
+		&self,
+		root: &mut DrawingArea<CanvasBackend, Shift>,
+	) -> Result<(), &'static str> {
+		let mut rows = root.split_evenly((4, 1));
+		line::draw(
+			&mut rows[0],
+			&self.history,
+			|data| data.generation_time,
+			self.ranges.generation_time.clone(),
+			"generation time",
+			self.forget
+		)
+		.ok_or("could not draw generation time plot")?;
+
+		ages::draw(
+			&mut rows[1],
+			&self.history,
+			"age distribution",
+			self.forget
+		)
+		.ok_or("could not draw age distribution plot")?;
+
+		line::draw(
+			&mut rows[2],
+			&self.history,
+			|data| data.dormant,
+			self.ranges.dormant.clone(),
+			"seed bank size",
+			self.forget
+		)
+		.ok_or("could not draw seed bank plot")?;
+
+		environment::draw(
+			&mut rows[3],
+			&self.history,
+			|data| &data.occupancy,
+			self.ranges.occupancy.clone(),
+			"occupancy per patch",
+			self.forget
+		)
+		.ok_or("could not draw occupancy plot")?;
+
+		Ok(())
+	}
+
+	fn draw_differentiation_tab(
+		&self,
+		root: &mut DrawingArea<CanvasBackend, Shift>,
+	) -> Result<(), &'static str> {
+		let mut rows = root.split_evenly((4, 1));
+		line::draw(
+			&mut rows[0],
+			&self.history,
+			|data| data.fst,
+			self.ranges.fst.clone(),
+			"F_ST on the neutral markers",
+			self.forget
+		)
+		.ok_or("could not draw F_ST plot")?;
+
+		line::draw(
+			&mut rows[1],
+			&self.history,
+			|data| data.qst,
+			self.ranges.qst.clone(),
+			"Q_ST on the trait",
+			self.forget
+		)
+		.ok_or("could not draw Q_ST plot")?;
+
+		line::draw(
+			&mut rows[2],
+			&self.history,
+			|data| data.heterozygosity,
+			self.ranges.heterozygosity.clone(),
+			"heterozygosity",
+			self.forget
+		)
+		.ok_or("could not draw heterozygosity plot")?;
+
+		line::draw(
+			&mut rows[3],
+			&self.history,
+			|data| data.morphs.len() as f64,
+			self.ranges.morphs.clone(),
+			"number of morphs",
+			self.forget
+		)
+		.ok_or("could not draw morphs plot")?;
+
+		Ok(())
+	}
+
 	fn draw(&mut self) -> Result<(), &'static str> {
 		let start = Instant::now();
 
@@ -278,6 +437,8 @@ impl DrawScheduler {
 		match self.tab {
 			Tab::General => self.draw_general_tab(&mut root),
 			Tab::Loci => self.draw_loci_tab(&mut root),
+			Tab::Ages => self.draw_ages_tab(&mut root),
+			Tab::Differentiation => self.draw_differentiation_tab(&mut root),
 		}?;
 
 		root.present()
@@ -1,23 +1,79 @@
 use core::ptr;
 use std::{
+	collections::VecDeque,
 	f64::consts::PI,
 	ops::{Deref, DerefMut},
+	path::{Component, Path, PathBuf},
 };
 
 use itertools::izip;
-use rand::{prelude::SliceRandom, thread_rng, Rng};
-use rand_distr::{Bernoulli, Binomial, Distribution, Normal, Uniform, WeightedAliasIndex};
+use rand::{prelude::SliceRandom, thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rand_distr::{
+	Bernoulli, Binomial, Distribution, Normal, Poisson, Uniform, WeightedAliasIndex, WeightedIndex,
+};
 use serde::{Deserialize, Serialize};
 use tinyvec::{tiny_vec, TinyVec};
 
+pub mod stats;
 mod test;
 
+// directory the environment series are read from, relative to where the simulation is started
+pub static SERIES_DIR: &str = "series";
+
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub struct Individual {
-	pub loci: TinyVec<[f64; 10]>,
+	pub loci:       TinyVec<[f64; 10]>,
+	// heritable dispersal probability of the modifier locus, m is used if None
+	pub dispersal:  Option<f64>,
+	// heritable mutation probability of the mutator locus, mutation_mu is used if None
+	pub mutation:   Option<f64>,
+	// heritable slope of the reaction norm on the environmental cue, not plastic if None
+	pub slope:      Option<f64>,
+	// amount of ticks the individual has survived as an adult, offspring count the ticks they
+	// spent dormant until they are born
+	pub age:        u32,
+	// mean age of the parents when the individual was conceived
+	pub parent_age: f64,
+	// sex of the individual, None for hermaphrodites
+	pub sex:        Option<Sex>,
+	// alleles of the neutral marker loci, laid out per haplotype like the loci, 64 bits wide so
+	// long runs cannot exhaust the alleles of the infinite-alleles model
+	pub markers:    Vec<i64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Sex {
+	Female,
+	Male,
+}
+
+impl Sex {
+	pub fn random(rng: &mut impl Rng) -> Sex {
+		match rng.gen_bool(0.5) {
+			true => Sex::Male,
+			false => Sex::Female,
+		}
+	}
 }
 
 impl Individual {
+	pub fn new(loci: TinyVec<[f64; 10]>) -> Individual {
+		Self {
+			loci,
+			..Default::default()
+		}
+	}
+
+	/// a newborn copy of the individual that remembers the age of its parent
+	pub fn offspring(&self) -> Individual {
+		Individual {
+			age: 0,
+			parent_age: self.age as f64,
+			..self.clone()
+		}
+	}
+
 	pub fn phenotype(&self) -> f64 { self.loci.iter().sum() }
 }
 
@@ -43,96 +99,124 @@ impl Patch {
 
 	////////////////////////////////////////////////////////////////////////////////////////////////
 
-	pub fn normal(patches: usize, patch_size: usize, loci: usize) -> Vec<Patch> {
-		let mut rng = thread_rng();
+	pub fn normal(
+		patches: usize,
+		patch_size: usize,
+		loci: usize,
+		rng: &mut impl Rng,
+	) -> Vec<Patch> {
 		let distr = Normal::new(0.0, 1.0 / loci as f64).unwrap();
-		let loci: TinyVec<[f64; 10]> = distr.sample_iter(&mut rng).take(loci).collect();
+		let loci: TinyVec<[f64; 10]> = distr.sample_iter(&mut *rng).take(loci).collect();
 		(0 .. patches)
 			.map(|_| Patch {
 				individuals: (0 .. patch_size)
-					.map(|_| Individual { loci: loci.clone() })
+					.map(|_| Individual::new(loci.clone()))
 					.collect(),
 			})
 			.collect()
 	}
 
-	pub fn normal_p(patches: usize, patch_size: usize, loci: usize) -> Vec<Patch> {
-		let mut rng = thread_rng();
+	pub fn normal_p(
+		patches: usize,
+		patch_size: usize,
+		loci: usize,
+		rng: &mut impl Rng,
+	) -> Vec<Patch> {
 		let distr = Normal::new(0.0, 1.0 / loci as f64).unwrap();
 		(0 .. patches)
 			.map(|_| {
-				let loci: TinyVec<[f64; 10]> = distr.sample_iter(&mut rng).take(loci).collect();
+				let loci: TinyVec<[f64; 10]> = distr.sample_iter(&mut *rng).take(loci).collect();
 				Patch {
 					individuals: (0 .. patch_size)
-						.map(|_| Individual { loci: loci.clone() })
+						.map(|_| Individual::new(loci.clone()))
 						.collect(),
 				}
 			})
 			.collect()
 	}
 
-	pub fn normal_i(patches: usize, patch_size: usize, loci: usize) -> Vec<Patch> {
-		let mut rng = thread_rng();
+	pub fn normal_i(
+		patches: usize,
+		patch_size: usize,
+		loci: usize,
+		rng: &mut impl Rng,
+	) -> Vec<Patch> {
 		let distr = Normal::new(0.0, 1.0 / loci as f64).unwrap();
 		(0 .. patches)
 			.map(|_| Patch {
 				individuals: (0 .. patch_size)
 					.map(|_| {
-						let loci = distr.sample_iter(&mut rng).take(loci).collect();
-						Individual { loci }
+						let loci = distr.sample_iter(&mut *rng).take(loci).collect();
+						Individual::new(loci)
 					})
 					.collect(),
 			})
 			.collect()
 	}
 
-	pub fn uniform(patches: usize, patch_size: usize, loci: usize) -> Vec<Patch> {
-		let mut rng = thread_rng();
+	pub fn uniform(
+		patches: usize,
+		patch_size: usize,
+		loci: usize,
+		rng: &mut impl Rng,
+	) -> Vec<Patch> {
 		let distr = Uniform::new(-1.0 / loci as f64, 1.0 / loci as f64);
-		let loci: TinyVec<[f64; 10]> = distr.sample_iter(&mut rng).take(loci).collect();
+		let loci: TinyVec<[f64; 10]> = distr.sample_iter(&mut *rng).take(loci).collect();
 		(0 .. patches)
 			.map(|_| Patch {
 				individuals: (0 .. patch_size)
-					.map(|_| Individual { loci: loci.clone() })
+					.map(|_| Individual::new(loci.clone()))
 					.collect(),
 			})
 			.collect()
 	}
 
-	pub fn uniform_p(patches: usize, patch_size: usize, loci: usize) -> Vec<Patch> {
-		let mut rng = thread_rng();
+	pub fn uniform_p(
+		patches: usize,
+		patch_size: usize,
+		loci: usize,
+		rng: &mut impl Rng,
+	) -> Vec<Patch> {
 		let distr = Uniform::new(-1.0 / loci as f64, 1.0 / loci as f64);
 		(0 .. patches)
 			.map(|_| {
-				let loci: TinyVec<[f64; 10]> = distr.sample_iter(&mut rng).take(loci).collect();
+				let loci: TinyVec<[f64; 10]> = distr.sample_iter(&mut *rng).take(loci).collect();
 				Patch {
 					individuals: (0 .. patch_size)
-						.map(|_| Individual { loci: loci.clone() })
+						.map(|_| Individual::new(loci.clone()))
 						.collect(),
 				}
 			})
 			.collect()
 	}
 
-	pub fn uniform_i(patches: usize, patch_size: usize, loci: usize) -> Vec<Patch> {
-		let mut rng = thread_rng();
+	pub fn uniform_i(
+		patches: usize,
+		patch_size: usize,
+		loci: usize,
+		rng: &mut impl Rng,
+	) -> Vec<Patch> {
 		let distr = Uniform::new(-1.0 / loci as f64, 1.0 / loci as f64);
 		(0 .. patches)
 			.map(|_| Patch {
 				individuals: (0 .. patch_size)
 					.map(|_| {
-						let loci = distr.sample_iter(&mut rng).take(loci).collect();
-						Individual { loci }
+						let loci = distr.sample_iter(&mut *rng).take(loci).collect();
+						Individual::new(loci)
 					})
 					.collect(),
 			})
 			.collect()
 	}
 
-	pub fn constant(patches: usize, patch_size: usize, loci_len: usize) -> Vec<Patch> {
-		let mut rng = thread_rng();
+	pub fn constant(
+		patches: usize,
+		patch_size: usize,
+		loci_len: usize,
+		rng: &mut impl Rng,
+	) -> Vec<Patch> {
 		let distr = Uniform::new(-1.0 / loci_len as f64, 1.0 / loci_len as f64);
-		let value = distr.sample(&mut rng);
+		let value = distr.sample(rng);
 		let mut loci = TinyVec::with_capacity(loci_len);
 		for _ in 0 .. loci_len {
 			loci.push(value);
@@ -140,63 +224,76 @@ impl Patch {
 		(0 .. patches)
 			.map(|_| Patch {
 				individuals: (0 .. patch_size)
-					.map(|_| Individual { loci: loci.clone() })
+					.map(|_| Individual::new(loci.clone()))
 					.collect(),
 			})
 			.collect()
 	}
 
-	pub fn constant_p(patches: usize, patch_size: usize, loci_len: usize) -> Vec<Patch> {
-		let mut rng = thread_rng();
+	pub fn constant_p(
+		patches: usize,
+		patch_size: usize,
+		loci_len: usize,
+		rng: &mut impl Rng,
+	) -> Vec<Patch> {
 		let distr = Uniform::new(-1.0 / loci_len as f64, 1.0 / loci_len as f64);
 		(0 .. patches)
 			.map(|_| {
-				let value = distr.sample(&mut rng);
+				let value = distr.sample(rng);
 				let mut loci = TinyVec::with_capacity(loci_len);
 				for _ in 0 .. loci_len {
 					loci.push(value);
 				}
 				Patch {
 					individuals: (0 .. patch_size)
-						.map(|_| Individual { loci: loci.clone() })
+						.map(|_| Individual::new(loci.clone()))
 						.collect(),
 				}
 			})
 			.collect()
 	}
 
-	pub fn constant_i(patches: usize, patch_size: usize, loci_len: usize) -> Vec<Patch> {
-		let mut rng = thread_rng();
+	pub fn constant_i(
+		patches: usize,
+		patch_size: usize,
+		loci_len: usize,
+		rng: &mut impl Rng,
+	) -> Vec<Patch> {
 		let distr = Uniform::new(-1.0 / loci_len as f64, 1.0 / loci_len as f64);
 		(0 .. patches)
 			.map(|_| Patch {
 				individuals: (0 .. patch_size)
 					.map(|_| {
-						let value = distr.sample(&mut rng);
+						let value = distr.sample(rng);
 						let mut loci = TinyVec::with_capacity(loci_len);
-						for i in 0 .. loci_len {
+						for _ in 0 .. loci_len {
 							loci.push(value);
 						}
-						Individual { loci }
+						Individual::new(loci)
 					})
 					.collect(),
 			})
 			.collect()
 	}
 
-	pub fn alternating(patches: usize, patch_size: usize, loci: usize, chance: f64) -> Vec<Patch> {
-		let mut rng = thread_rng();
+	pub fn alternating(
+		patches: usize,
+		patch_size: usize,
+		loci: usize,
+		chance: f64,
+		rng: &mut impl Rng,
+	) -> Vec<Patch> {
 		let distr = Bernoulli::new(chance).unwrap();
 		(0 .. patches)
 			.map(|_| Patch {
 				individuals: (0 .. patch_size)
 					.map(|_| {
 						let loci = distr
-							.sample_iter(&mut rng)
+							.sample_iter(&mut *rng)
 							.take(loci)
 							.map(|x| if x { 1.0 } else { -1.0 })
 							.collect();
-						Individual { loci }
+						Individual::new(loci)
 					})
 					.collect(),
 			})
@@ -205,44 +302,52 @@ impl Patch {
 
 	////////////////////////////////////////////////////////////////////////////////////////////////
 
-	pub fn random_env(len: usize) -> Vec<f64> {
-		let mut rng = thread_rng();
+	pub fn random_env(len: usize, rng: &mut impl Rng) -> Vec<f64> {
 		let distr = Uniform::new(-0.5, 0.5);
-		distr.sample_iter(&mut rng).take(len).collect()
+		distr.sample_iter(&mut *rng).take(len).collect()
 	}
 
-	pub fn alternating_env(len: usize, chance: f64) -> Vec<f64> {
-		let mut rng = thread_rng();
+	pub fn alternating_env(len: usize, chance: f64, rng: &mut impl Rng) -> Vec<f64> {
 		let distr = Bernoulli::new(chance).unwrap();
 		distr
-			.sample_iter(&mut rng)
+			.sample_iter(&mut *rng)
 			.take(len)
 			.map(|x| if x { 0.5 } else { -0.5 })
 			.collect()
 	}
 
-	pub fn sine_env(len: usize, tick: u64, factor: f64) -> Vec<f64> {
+	/// sinusoid with the given period in ticks, the phases of the patches are spread evenly over
+	/// `phase_spread` full cycles
+	pub fn sine_env(
+		len: usize,
+		tick: u64,
+		period: f64,
+		amplitude: f64,
+		phase_spread: f64,
+		mean: f64,
+	) -> Vec<f64> {
 		(0 .. len)
-			.map(|x| 0.5 * (2.0 * (x as f64 * PI / len as f64 + tick as f64 * PI / factor)).sin())
+			.map(|x| {
+				let phase = phase_spread * x as f64 / len as f64 + tick as f64 / period;
+				mean + amplitude * (2.0 * PI * phase).sin()
+			})
 			.collect()
 	}
 
-	pub fn random_walk_env(p: Vec<f64>) -> Vec<f64> {
-		let mut rng = thread_rng();
+	pub fn random_walk_env(p: Vec<f64>, rng: &mut impl Rng) -> Vec<f64> {
 		let distr = Normal::new(0.0, 0.001).unwrap();
 		p.into_iter()
-			.map(|x| (x + distr.sample(&mut rng)).clamp(-0.5, 0.5))
+			.map(|x| (x + distr.sample(rng)).clamp(-0.5, 0.5))
 			.collect()
 	}
 
-	pub fn constant_with_jumps_env(p: Vec<f64>) -> Vec<f64> {
-		let mut rng = thread_rng();
+	pub fn constant_with_jumps_env(p: Vec<f64>, rng: &mut impl Rng) -> Vec<f64> {
 		let distr = Bernoulli::new(0.0001).unwrap();
 		let random = Uniform::new(-1.0, 1.0);
 		p.into_iter()
 			.map(|x| {
-				if distr.sample(&mut rng) {
-					random.sample(&mut rng)
+				if distr.sample(rng) {
+					random.sample(rng)
 				} else {
 					x
 				}
@@ -250,6 +355,52 @@ impl Patch {
 			.collect()
 	}
 
+	/// AR(1) process per patch with the given stationary variance, the innovations share a common
+	/// component to give the patches the requested correlation
+	pub fn correlated_env(
+		p: Vec<f64>,
+		variance: f64,
+		autocorrelation: f64,
+		correlation: f64,
+		rng: &mut impl Rng,
+	) -> Vec<f64> {
+		let distr = Normal::new(0.0, variance.sqrt()).unwrap();
+		let common = distr.sample(rng);
+		let innovation = (1.0 - autocorrelation * autocorrelation).sqrt();
+		p.into_iter()
+			.map(|x| {
+				let epsilon =
+					correlation.sqrt() * common + (1.0 - correlation).sqrt() * distr.sample(rng);
+				autocorrelation * x + innovation * epsilon
+			})
+			.collect()
+	}
+
+	/// component of the environment from the row of the series for the given tick, after the last
+	/// row the series either starts over or holds the last value, a row holds a single value, a
+	/// value per patch or a value per patch and component, the components of a patch side by side
+	pub fn series_env(
+		series: &[Vec<f64>],
+		len: usize,
+		component: usize,
+		components: usize,
+		tick: u64,
+		repeat: bool,
+	) -> Vec<f64> {
+		let index = match repeat {
+			true => tick as usize % series.len(),
+			false => (tick as usize).min(series.len() - 1),
+		};
+		let row = &series[index];
+		(0 .. len)
+			.map(|i| match row.len() {
+				1 => row[0],
+				columns if columns == len * components => row[i * components + component],
+				_ => row[i],
+			})
+			.collect()
+	}
+
 	////////////////////////////////////////////////////////////////////////////////////////////////
 }
 
@@ -279,6 +430,39 @@ pub enum InitialPopulation {
 	AlternatingThird,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Capacities {
+	// the individuals are divided evenly over the patches
+	Even,
+	// carrying capacity of every patch, the individuals are ignored
+	List(Vec<usize>),
+	// every patch holds ratio times as many individuals as the previous one
+	Geometric { ratio: f64 },
+}
+
+impl Capacities {
+	/// carrying capacity of every patch, every patch must have room for at least one individual
+	pub fn resolve(&self, patches: usize, individuals: usize) -> Result<Vec<usize>, &'static str> {
+		let capacities = match self {
+			Capacities::Even if patches == 0 => Vec::new(),
+			Capacities::Even => vec![individuals / patches; patches],
+			Capacities::List(list) if list.len() != patches =>
+				return Err("There must be a carrying capacity for every patch."),
+			Capacities::List(list) => list.clone(),
+			Capacities::Geometric { ratio } if !ratio.is_finite() || *ratio <= 0.0 =>
+				return Err("The ratio between the carrying capacities must be strictly positive."),
+			Capacities::Geometric { ratio } => {
+				let share: Vec<f64> = (0 .. patches).map(|i| ratio.powi(i as i32)).collect();
+				apportion(&share, individuals)
+			},
+		};
+		match capacities.contains(&0) {
+			true => Err("Every patch must have room for at least one individual."),
+			false => Ok(capacities),
+		}
+	}
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct InitConfig {
 	// max ticks, unlimited if None (=100000)
@@ -288,9 +472,82 @@ pub struct InitConfig {
 	pub patches:     usize,
 	pub individuals: usize,
 	pub loci:        usize,
+	// amount of traits the loci are divided over (=1)
+	pub traits:      usize,
 
 	// diploid or haploid
-	pub diploid: bool,
+	pub diploid:           bool,
+	// how the loci combine into the phenotype
+	pub genotype_map:      GenotypeMap,
+	// crossover probability between every pair of adjacent loci, derived from rec if None
+	pub recombination_map: Option<Vec<f64>>,
+	// carrying capacity of every patch
+	pub capacities:        Capacities,
+
+	// random number generator seed, random if None
+	pub seed: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum GenotypeMap {
+	// all loci add up to the phenotype
+	Additive,
+	// at every diploid locus the larger allele has weight h and the smaller one 1 - h, so h = 0.5
+	// is additive, haploids stay additive
+	Dominance { h: f64 },
+	// the locus effects add up, plus epsilon times the product of every pair of locus effects
+	Epistasis { epsilon: f64 },
+}
+
+impl GenotypeMap {
+	/// phenotype of an individual, diploid loci hold the first haplotype followed by the second
+	pub fn phenotype(&self, individual: &Individual, diploid: bool) -> f64 {
+		let (first, second) = match diploid {
+			true => individual.split_at(individual.len() / 2),
+			false => (&individual[..], &[][..]),
+		};
+		match self {
+			GenotypeMap::Additive => individual.phenotype(),
+			GenotypeMap::Dominance { .. } if !diploid => individual.phenotype(),
+			GenotypeMap::Dominance { h } => first
+				.iter()
+				.zip(second)
+				.map(|(a, b)| 2.0 * (a.min(*b) + h * (a - b).abs()))
+				.sum(),
+			GenotypeMap::Epistasis { epsilon } => {
+				let effects: Vec<f64> = match diploid {
+					true => first.iter().zip(second).map(|(a, b)| a + b).collect(),
+					false => first.to_vec(),
+				};
+				let sum: f64 = effects.iter().sum();
+				let squares: f64 = effects.iter().map(|x| x * x).sum();
+				// the sum over all pairs of x_i*x_j equals ((sum x)^2 - sum x^2)/2
+				sum + epsilon * (sum * sum - squares) / 2.0
+			},
+		}
+	}
+
+	/// phenotypes of every trait, locus i of each haplotype belongs to trait i % traits
+	pub fn traits(&self, individual: &Individual, diploid: bool, traits: usize) -> Vec<f64> {
+		if traits <= 1 {
+			return vec![self.phenotype(individual, diploid)];
+		}
+		let haplotype = match diploid {
+			true => individual.len() / 2,
+			false => individual.len(),
+		};
+		(0 .. traits)
+			.map(|t| {
+				let loci = individual
+					.iter()
+					.enumerate()
+					.filter(|(i, _)| i % haplotype.max(1) % traits == t)
+					.map(|(_, x)| *x)
+					.collect();
+				self.phenotype(&Individual::new(loci), diploid)
+			})
+			.collect()
+	}
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -298,79 +555,772 @@ pub enum Environment {
 	Random,
 	AlternatingHalf,
 	AlternatingThird,
-	SineSlow,
-	SineMedium,
-	SineFast,
+	// sinusoid over time, shifted in phase between the patches
+	Sine {
+		period:       f64,
+		amplitude:    f64,
+		phase_spread: f64,
+		mean:         f64,
+	},
 	RandomWalk,
 	Constant,
 	ConstantWithJumps,
+	// normally distributed optima around 0 with the given variance, correlated in time (AR(1))
+	// and between the patches
+	Correlated {
+		variance:        f64,
+		autocorrelation: f64,
+		correlation:     f64,
+	},
+	// optima read from a csv file in the series directory with a row per tick and a column per
+	// patch (or a single column shared by all patches, or a column per patch and trait), the
+	// series starts over when repeat is set and holds otherwise
+	FromSeries {
+		path:   String,
+		repeat: bool,
+	},
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Dispersal {
+	// island model, dispersers can end up in any patch
+	Global,
+	// dispersers move to a neighbouring patch on a chain, or on a ring if circular
+	SteppingStone { circular: bool },
+	// dispersers move to one of the four neighbouring patches on a grid with the given width
+	Lattice { width: usize, circular: bool },
+	// dispersers move to another patch with a chance decaying exponentially with the distance
+	Kernel { scale: f64 },
+	// dispersers move from patch i to patch j with relative chance matrix[i][j]
+	Matrix(Vec<Vec<f64>>),
+}
+
+impl Dispersal {
+	/// relative chance for a disperser to move from one patch to another, None for the island model
+	pub fn matrix(&self, len: usize) -> Option<Vec<Vec<f64>>> {
+		let build = |weight: &dyn Fn(usize, usize) -> f64| {
+			(0 .. len)
+				.map(|i| (0 .. len).map(|j| weight(i, j)).collect())
+				.collect()
+		};
+		let distance = |i: usize, j: usize, len: usize, circular: bool| {
+			let distance = i.max(j) - i.min(j);
+			match circular {
+				true => distance.min(len - distance),
+				false => distance,
+			}
+		};
+
+		match self {
+			Dispersal::Global => None,
+			Dispersal::SteppingStone { circular } => Some(build(&|i, j| {
+				(distance(i, j, len, *circular) == 1) as u8 as f64
+			})),
+			Dispersal::Lattice { width, circular } => {
+				let rows = (len + width - 1) / width;
+				Some(build(&|i, j| {
+					let x = distance(i % width, j % width, *width, *circular);
+					let y = distance(i / width, j / width, rows, *circular);
+					(x + y == 1) as u8 as f64
+				}))
+			},
+			Dispersal::Kernel { scale } => Some(build(&|i, j| match i == j {
+				true => 0.0,
+				false => (-((i.max(j) - i.min(j)) as f64) / scale).exp(),
+			})),
+			Dispersal::Matrix(matrix) => Some(matrix.clone()),
+		}
+	}
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum FitnessFunction {
+	// e^(-d^2/(2*sigma^2)) with d the distance between phenotype and optimum
+	Gaussian,
+	// 1 - d^2/(2*sigma^2), truncated at 0
+	Quadratic,
+	// 1 within sigma of the optimum, 0 elsewhere
+	Truncation,
+	// 1 within width of the optimum, declining as a gaussian beyond
+	Plateau { width: f64 },
+}
+
+impl FitnessFunction {
+	/// relative fitness of a phenotype at the given distance from the optimum
+	pub fn fitness(&self, distance: f64, sigma: f64) -> f64 {
+		let distance = distance.abs();
+		match self {
+			FitnessFunction::Gaussian => (-(distance.powi(2) / (2.0 * sigma.powi(2)))).exp(),
+			FitnessFunction::Quadratic => (1.0 - distance.powi(2) / (2.0 * sigma.powi(2))).max(0.0),
+			FitnessFunction::Truncation => (distance <= sigma) as u8 as f64,
+			FitnessFunction::Plateau { width } =>
+				(-((distance - width).max(0.0).powi(2) / (2.0 * sigma.powi(2)))).exp(),
+		}
+	}
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum MutationModel {
+	// add a gaussian effect with standard deviation mutation_sigma
+	Gaussian,
+	// add a gaussian effect rounded to a multiple of mutation_step
+	DiscreteGaussian,
+	// add or subtract mutation_step with equal chance
+	Stepwise,
+	// replace the allelic value by a gaussian draw around 0 with standard deviation
+	// mutation_sigma
+	HouseOfCards,
+	// discretised gaussian effect, reflected back between min and max
+	Bounded { min: f64, max: f64 },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum MarkerModel {
+	// every mutation creates an allele that never existed before
+	InfiniteAlleles,
+	// the repeat count of a microsatellite goes up or down by one with equal chance
+	Stepwise,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Markers {
+	// amount of neutral marker loci on a haplotype
+	pub loci:  usize,
+	// mutation probability of every marker allele
+	pub mu:    f64,
+	// how a mutation changes a marker allele
+	pub model: MarkerModel,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Plasticity {
+	// mutational effect size of the reaction norm slope
+	pub sigma:     f64,
+	// standard deviation of the noise on the environmental cue (=0)
+	pub cue_noise: f64,
+	// amount of ticks the environmental cue lags behind the environment (=0)
+	pub cue_lag:   usize,
+	// cost of plasticity, fitness is multiplied by e^(-cost*slope^2) (=0)
+	pub cost:      f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Dioecy {
+	// the optimum of males lies offset above and that of females offset below the optimum of the
+	// patch (=0)
+	pub offset: f64,
+	// selection strength (standard deviation) of males, selection_sigma if None, a selection
+	// covariance matrix is scaled by (sigma/selection_sigma)^2 for males
+	pub sigma:  Option<f64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SeedBank {
+	// probability that an offspring becomes dormant instead of competing for a spot
+	pub dormancy:    f64,
+	// probability that a dormant individual germinates every tick
+	pub germination: f64,
+	// probability that a dormant individual dies every tick
+	pub decay:       f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum PopulationDynamics {
+	// N' = w*N/(1 + (r_max - 1)*N/K)
+	BevertonHolt,
+	// N' = w*N*r_max^(-N/K)
+	Ricker,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Config {
 	// trait mutation probability (=0.01)
-	pub mutation_mu:     f64,
+	pub mutation_mu:            f64,
 	// expected mutational effect size (=0.01)
-	pub mutation_sigma:  f64,
+	pub mutation_sigma:         f64,
 	// bin size for mutational effects (=0.01)
-	pub mutation_step:   f64,
+	pub mutation_step:          f64,
+	// how a mutation changes the allelic value
+	pub mutation_model:         MutationModel,
+	// mutation probability of every locus, mutation_mu for all loci if None
+	pub mutation_rates:         Option<Vec<f64>>,
+	// mutational effect size of the heritable mutation probability, everybody mutates with
+	// mutation_mu if None
+	pub mutator_sigma:          Option<f64>,
+	// neutral marker loci that are inherited and migrate without being selected, no markers if
+	// None (=None)
+	pub markers:                Option<Markers>,
 	// recombinational probality (=0.01)
-	pub rec:             f64,
+	pub rec:                    f64,
+	// probability that an individual fertilises itself instead of mating with another (=0)
+	pub selfing:                f64,
+	// strength a of assortative mating, a mate with a phenotype at distance d is preferred with
+	// weight e^(-a*d^2) (=0)
+	pub assortative_mating:     f64,
 	// selection strength (standard deviation)
-	pub selection_sigma: f64,
+	pub selection_sigma:        f64,
+	// shape of the fitness function around the optimum
+	pub fitness:                FitnessFunction,
+	// reproductive success at the optimum (=1)
+	pub r_max:                  f64,
+	// width of the competition kernel, no frequency dependent competition if None
+	pub competition_sigma:      Option<f64>,
+	// offset and slope of the optimum of every trait on its own component of the environment,
+	// every trait follows its component if empty
+	pub optima:                 Vec<(f64, f64)>,
+	// covariance matrix of the selection surface over the traits, selection_sigma^2 on the
+	// diagonal if None
+	pub selection_covariance:   Option<Vec<Vec<f64>>>,
+	// separate sexes with sex-specific selection, hermaphrodites if None (=None)
+	pub dioecy:                 Option<Dioecy>,
+	// reaction norm of the first trait on an environmental cue, no plasticity if None
+	pub plasticity:             Option<Plasticity>,
+	// variance of the developmental noise on the phenotype (=0)
+	pub developmental_variance: f64,
 	// generation overlap
-	pub gamma:           f64,
+	pub gamma:                  f64,
+	// survival probability of every age class, the last one also holds for all older ages, gamma
+	// for every age if None
+	pub survival:               Option<Vec<f64>>,
+	// dormant pool of offspring in every patch, no seed bank if None
+	pub seed_bank:              Option<SeedBank>,
 	// dispersal parameter
-	pub m:               f64,
-	// environment update function
-	pub environment:     Environment,
+	pub m:                      f64,
+	// mutational effect size of the heritable dispersal probability, everybody disperses with m
+	// if None
+	pub dispersal_sigma:        Option<f64>,
+	// fraction of hard selection between 0 and 1, the soft share of the offspring keeps its patch
+	// full while the patch sizes follow the productivity of the hard share (=0)
+	pub hard_selection:         f64,
+	// regulate the size of every patch by the mean fitness of its adults instead of keeping it
+	// full, patches can then shrink, go extinct and be recolonised (=None)
+	pub population_dynamics:    Option<PopulationDynamics>,
+	// dispersal topology between the patches
+	pub dispersal:              Dispersal,
+	// environment update function, every trait has its own component of the environment that
+	// follows its own realisation of the process
+	pub environment:            Environment,
+}
+
+impl Config {
+	/// check the parameters that depend on the patches, traits and loci of the population
+	pub fn validate(&self, state: &State) -> Result<(), &'static str> {
+		let (patches, traits) = (state.patches.len(), state.traits);
+		if let Some(rates) = &self.mutation_rates {
+			if rates.len() != state.loci() {
+				return Err("There must be a mutation probability for every locus.");
+			}
+			if rates.iter().any(|rate| !(0.0 ..= 1.0).contains(rate)) {
+				return Err("Mutation probabilities must lie between 0 and 1.");
+			}
+		}
+		if matches!(self.mutator_sigma, Some(sigma) if sigma.is_nan() || sigma <= 0.0) {
+			return Err(
+				"The mutational effect size of the mutation probability must be strictly positive.",
+			);
+		}
+		if self.mutator_sigma.is_some() && self.mutation_rates.is_some() {
+			return Err(
+				"An evolving mutation probability cannot be combined with per locus mutation \
+				 probabilities.",
+			);
+		}
+		if let Some(markers) = &self.markers {
+			if markers.loci == 0 {
+				return Err("There must be at least one marker locus.");
+			}
+			if !(0.0 ..= 1.0).contains(&markers.mu) {
+				return Err("The mutation probability of the markers must lie between 0 and 1.");
+			}
+		}
+		if let MutationModel::Bounded { min, max } = self.mutation_model {
+			if min.is_nan() || max.is_nan() || min >= max {
+				return Err(
+					"The lower bound of the allelic values must lie below the upper bound.",
+				);
+			}
+		}
+		if !(0.0 ..= 1.0).contains(&self.hard_selection) {
+			return Err("The fraction of hard selection must lie between 0 and 1.");
+		}
+		if self.r_max.is_nan() || self.r_max <= 0.0 {
+			return Err("The maximal reproductive success must be strictly positive.");
+		}
+		if self.population_dynamics.is_some() && self.r_max <= 1.0 {
+			return Err(
+				"The maximal reproductive success must exceed 1 for a patch to be able to grow.",
+			);
+		}
+		if matches!(self.competition_sigma, Some(sigma) if sigma.is_nan() || sigma <= 0.0) {
+			return Err("The width of the competition kernel must be strictly positive.");
+		}
+		if !self.optima.is_empty() && self.optima.len() != traits {
+			return Err("There must be an optimum for every trait.");
+		}
+		if let Some(covariance) = &self.selection_covariance {
+			if covariance.len() != traits || covariance.iter().any(|row| row.len() != traits) {
+				return Err(
+					"The selection covariance matrix must have a row and a column for every trait.",
+				);
+			}
+			let symmetric = (0 .. traits)
+				.all(|i| (0 .. i).all(|j| (covariance[i][j] - covariance[j][i]).abs() < 1e-12));
+			if !symmetric || cholesky(covariance).is_none() {
+				return Err(
+					"The selection covariance matrix must be symmetric and positive definite.",
+				);
+			}
+		}
+		if matches!(self.dispersal_sigma, Some(sigma) if sigma.is_nan() || sigma <= 0.0) {
+			return Err(
+				"The mutational effect size of the dispersal probability must be strictly \
+				 positive.",
+			);
+		}
+		if let Some(survival) = &self.survival {
+			if survival.is_empty() {
+				return Err("There must be a survival probability for at least one age class.");
+			}
+			if survival.iter().any(|p| !(0.0 ..= 1.0).contains(p)) {
+				return Err("Survival probabilities must lie between 0 and 1.");
+			}
+		}
+		if let Some(seed_bank) = &self.seed_bank {
+			let probabilities = [seed_bank.dormancy, seed_bank.germination, seed_bank.decay];
+			if probabilities.iter().any(|p| !(0.0 ..= 1.0).contains(p)) {
+				return Err(
+					"The dormancy, germination and decay probabilities must lie between 0 and 1.",
+				);
+			}
+			if seed_bank.germination == 0.0 && seed_bank.decay == 0.0 {
+				return Err("Dormant individuals must either germinate or decay.");
+			}
+		}
+		if self.developmental_variance.is_nan() || self.developmental_variance < 0.0 {
+			return Err("The variance of the developmental noise cannot be negative.");
+		}
+		if !(0.0 ..= 1.0).contains(&self.selfing) {
+			return Err("The selfing rate must lie between 0 and 1.");
+		}
+		if self.assortative_mating.is_nan() || self.assortative_mating < 0.0 {
+			return Err("The strength of assortative mating cannot be negative.");
+		}
+		if self.selfing > 0.0 && self.dioecy.is_some() {
+			return Err("Individuals cannot fertilise themselves with separate sexes.");
+		}
+		if let Some(dioecy) = &self.dioecy {
+			if !state.diploid {
+				return Err("Separate sexes require a diploid population.");
+			}
+			if !dioecy.offset.is_finite() {
+				return Err("The offset between the optima of the sexes must be a number.");
+			}
+			if matches!(dioecy.sigma, Some(sigma) if sigma.is_nan() || sigma <= 0.0) {
+				return Err("The selection strength of males must be strictly positive.");
+			}
+		}
+		if let Some(plasticity) = &self.plasticity {
+			if plasticity.sigma.is_nan() || plasticity.sigma <= 0.0 {
+				return Err(
+					"The mutational effect size of the reaction norm slope must be strictly \
+					 positive.",
+				);
+			}
+			if plasticity.cue_noise.is_nan() || plasticity.cue_noise < 0.0 {
+				return Err("The noise on the environmental cue cannot be negative.");
+			}
+			if plasticity.cost.is_nan() || plasticity.cost < 0.0 {
+				return Err("The cost of plasticity cannot be negative.");
+			}
+		}
+		if let FitnessFunction::Plateau { width } = self.fitness {
+			if width.is_nan() || width < 0.0 {
+				return Err("The width of the fitness plateau cannot be negative.");
+			}
+		}
+		match &self.dispersal {
+			Dispersal::Lattice { width: 0, .. } =>
+				Err("The lattice width must be strictly positive."),
+			Dispersal::Kernel { scale } if scale.is_nan() || *scale <= 0.0 =>
+				Err("The dispersal kernel scale must be strictly positive."),
+			Dispersal::Matrix(matrix)
+				if matrix.len() != patches || matrix.iter().any(|row| row.len() != patches) =>
+				Err("The migration matrix must have a row and a column for every patch."),
+			Dispersal::Matrix(matrix)
+				if matrix.iter().flatten().any(|x| x.is_nan() || *x < 0.0) =>
+				Err("The migration matrix cannot contain negative values."),
+			_ => Ok(()),
+		}?;
+		match &self.environment {
+			Environment::Sine { period, .. } if !period.is_finite() || *period <= 0.0 =>
+				Err("The period of the sinusoid must be strictly positive."),
+			Environment::FromSeries { path, .. } => read_series(path, patches, traits).map(|_| ()),
+			Environment::Correlated { variance, .. } if variance.is_nan() || *variance < 0.0 =>
+				Err("The environmental variance cannot be negative."),
+			Environment::Correlated {
+				autocorrelation, ..
+			} if !(-1.0 ..= 1.0).contains(autocorrelation) =>
+				Err("The temporal autocorrelation must lie between -1 and 1."),
+			Environment::Correlated { correlation, .. } if !(0.0 ..= 1.0).contains(correlation) =>
+				Err("The correlation between patches must lie between 0 and 1."),
+			_ => Ok(()),
+		}
+	}
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct State {
-	pub tick:    u64,
-	pub patches: Vec<(Patch, f64)>,
-	pub diploid: bool,
+	pub tick:              u64,
+	// every patch with its environment, a component for every trait
+	pub patches:           Vec<(Patch, Vec<f64>)>,
+	pub diploid:           bool,
+	// how the loci combine into the phenotype
+	pub genotype_map:      GenotypeMap,
+	// amount of traits the loci are divided over
+	pub traits:            usize,
+	// carrying capacity of every patch
+	pub capacities:        Vec<usize>,
+	// crossover probability between every pair of adjacent loci, derived from rec if None
+	pub recombination_map: Option<Vec<f64>>,
+	// environmental cue per patch the reaction norms respond to, empty without plasticity
+	pub cues:              Vec<f64>,
+	// realised narrow-sense heritability of the first trait, the variance of the breeding values
+	// over the phenotypic variance, None without phenotypic variance
+	pub heritability:      Option<f64>,
+	// mean age of the parents of the last offspring plus one and the ticks the offspring spent
+	// dormant, None if nobody was born
+	pub generation_time:   Option<f64>,
+	// dormant individuals in the seed bank of every patch
+	pub dormant:           Vec<Patch>,
+	// F_ST on the neutral markers, None without markers or marker variation
+	pub fst:               Option<f64>,
+	// Q_ST on the additive genetic value of the first trait, None without genetic variance
+	pub qst:               Option<f64>,
+
+	// seed the random number generator was created with
+	pub seed: u64,
+	#[serde(skip, default = "ChaCha8Rng::from_entropy")]
+	pub rng:  ChaCha8Rng,
+
+	// environment series and the file it was read from
+	#[serde(skip)]
+	series:  Option<(String, Vec<Vec<f64>>)>,
+	// environments of the previous ticks, the most recent first
+	#[serde(skip)]
+	history: VecDeque<Vec<f64>>,
+	// developmental noise on the first trait of every individual in every patch
+	#[serde(skip)]
+	noise:   Vec<Vec<f64>>,
+	// which adults in every patch die this tick under an age dependent survival schedule
+	#[serde(skip)]
+	dead:    Vec<Vec<bool>>,
+	// reproductive success of every adult in every patch in the last tick
+	#[serde(skip)]
+	success: Vec<Vec<f64>>,
+	// last allele created by an infinite-alleles mutation
+	alleles: i64,
 }
 
 impl State {
-	// update the environment
-	pub fn environment(&mut self, environment: &Environment, tick: u64) {
-		let len = self.patches.len();
-		let new = match environment {
-			Environment::Random => Patch::random_env(len),
-			Environment::AlternatingHalf => Patch::alternating_env(len, 0.5),
-			Environment::AlternatingThird => Patch::alternating_env(len, 2.0 / 3.0),
-			Environment::SineSlow => Patch::sine_env(len, tick, 70_000.0),
-			Environment::SineMedium => Patch::sine_env(len, tick, 20_000.0),
-			Environment::SineFast => Patch::sine_env(len, tick, 7_000.0),
-			Environment::RandomWalk =>
-				Patch::random_walk_env(self.patches.iter().map(|(_, x)| *x).collect()),
-			Environment::Constant => return,
-			Environment::ConstantWithJumps =>
-				Patch::constant_with_jumps_env(self.patches.iter().map(|(_, x)| *x).collect()),
+	pub fn new(patches: Vec<(Patch, Vec<f64>)>, diploid: bool, seed: u64) -> State {
+		let capacities = patches.iter().map(|(patch, _)| patch.len()).collect();
+		Self {
+			tick: 0,
+			patches,
+			diploid,
+			genotype_map: GenotypeMap::Additive,
+			traits: 1,
+			capacities,
+			recombination_map: None,
+			cues: Vec::new(),
+			heritability: None,
+			generation_time: None,
+			dormant: Vec::new(),
+			fst: None,
+			qst: None,
+			seed,
+			rng: ChaCha8Rng::seed_from_u64(seed),
+			series: None,
+			history: VecDeque::new(),
+			noise: Vec::new(),
+			dead: Vec::new(),
+			success: Vec::new(),
+			alleles: 0,
+		}
+	}
+
+	/// give every individual without a sex a random one under separate sexes, or turn everybody
+	/// into a hermaphrodite without
+	pub fn sexes(&mut self, dioecy: Option<&Dioecy>) {
+		let rng = &mut self.rng;
+		for individual in self
+			.patches
+			.iter_mut()
+			.flat_map(|(patch, _)| patch.iter_mut())
+		{
+			individual.sex = match dioecy {
+				Some(_) => individual.sex.or_else(|| Some(Sex::random(rng))),
+				None => None,
+			};
+		}
+	}
+
+	/// give every individual the marker loci of the configuration, new loci start out with allele
+	/// 0, or remove all markers without
+	pub fn markers(&mut self, markers: Option<&Markers>) {
+		let len = markers.map_or(0, |markers| markers.loci * if self.diploid { 2 } else { 1 });
+		for individual in self
+			.patches
+			.iter_mut()
+			.flat_map(|(patch, _)| patch.iter_mut())
+		{
+			individual.markers.resize(len, 0);
+		}
+	}
+
+	/// forget the heritable dispersal probabilities without an evolving dispersal probability, so
+	/// everybody disperses with m again
+	pub fn dispersal_modifier(&mut self, dispersal_sigma: Option<f64>) {
+		if dispersal_sigma.is_some() {
+			return;
+		}
+		for individual in self
+			.patches
+			.iter_mut()
+			.flat_map(|(patch, _)| patch.iter_mut())
+		{
+			individual.dispersal = None;
+		}
+	}
+
+	/// forget the heritable mutation probabilities without an evolving mutation probability, so
+	/// everybody mutates with mutation_mu again
+	pub fn mutator(&mut self, mutator_sigma: Option<f64>) {
+		if mutator_sigma.is_some() {
+			return;
+		}
+		for individual in self
+			.patches
+			.iter_mut()
+			.flat_map(|(patch, _)| patch.iter_mut())
+		{
+			individual.mutation = None;
+		}
+	}
+
+	/// phenotype of an individual under the genotype-phenotype map, the first trait if there are
+	/// several
+	pub fn phenotype(&self, individual: &Individual) -> f64 { self.traits(individual)[0] }
+
+	/// amount of loci on a haplotype
+	pub fn loci(&self) -> usize {
+		let len = self
+			.patches
+			.iter()
+			.find_map(|(patch, _)| patch.first())
+			.map_or(0, |individual| individual.len());
+		match self.diploid {
+			true => len / 2,
+			false => len,
+		}
+	}
+
+	/// phenotypes of every trait of an individual
+	pub fn traits(&self, individual: &Individual) -> Vec<f64> {
+		self.genotype_map
+			.traits(individual, self.diploid, self.traits)
+	}
+
+	/// phenotypes of every trait of an individual in a patch, the first trait is shifted by the
+	/// reaction norm on the cue of the patch
+	pub fn expressed(&self, individual: &Individual, patch: usize) -> Vec<f64> {
+		let mut traits = self.traits(individual);
+		if let (Some(slope), Some(cue)) = (individual.slope, self.cues.get(patch)) {
+			traits[0] += slope * cue;
+		}
+		traits
+	}
+
+	/// read the environmental cue of every patch, lagging behind and with noise, or forget the
+	/// cues and the history without plasticity
+	pub fn cue(&mut self, plasticity: Option<&Plasticity>) {
+		let plasticity = match plasticity {
+			Some(plasticity) => plasticity,
+			None => {
+				self.cues.clear();
+				self.history.clear();
+				return;
+			},
+		};
+		// the reaction norm of the first trait responds to its component of the environment
+		self.history
+			.push_front(self.patches.iter().map(|(_, env)| env[0]).collect());
+		self.history.truncate(plasticity.cue_lag + 1);
+		// the oldest environment that is remembered if the simulation is younger than the lag
+		let lagged = &self.history[self.history.len() - 1];
+		let rng = &mut self.rng;
+		let normal = Normal::new(0.0, plasticity.cue_noise).unwrap();
+		self.cues = lagged.iter().map(|env| env + normal.sample(rng)).collect();
+	}
+
+	/// draw the developmental noise of every individual and determine the realised narrow-sense
+	/// heritability, the variance of the breeding values over the variance of the phenotype that
+	/// is selected on
+	pub fn development(&mut self, developmental_variance: f64) {
+		let rng = &mut self.rng;
+		let normal = Normal::new(0.0, developmental_variance.sqrt()).unwrap();
+		self.noise = match developmental_variance > 0.0 {
+			true => self
+				.patches
+				.iter()
+				.map(|(patch, _)| patch.iter().map(|_| normal.sample(rng)).collect())
+				.collect(),
+			false => Vec::new(),
 		};
-		for ((_, env), new) in self.patches.iter_mut().zip(new) {
-			*env = new;
+
+		let additive: Vec<f64> = stats::breeding_values(self).into_iter().flatten().collect();
+		let mut phenotypes = Vec::new();
+		for (index, (patch, _)) in self.patches.iter().enumerate() {
+			for (i, individual) in patch.iter().enumerate() {
+				let noise = self.noise.get(index).map_or(0.0, |noise| noise[i]);
+				phenotypes.push(self.expressed(individual, index)[0] + noise);
+			}
 		}
+		let phenotypic = variance(&phenotypes);
+		self.heritability = (phenotypic > 0.0).then(|| variance(&additive) / phenotypic);
+	}
+
+	/// update the environment, fails if the environment series cannot be read
+	pub fn environment(
+		&mut self,
+		environment: &Environment,
+		tick: u64,
+	) -> Result<(), &'static str> {
+		let (len, traits) = (self.patches.len(), self.traits);
+		if let Environment::FromSeries { path, .. } = environment {
+			if self
+				.series
+				.as_ref()
+				.map_or(true, |(loaded, _)| loaded != path)
+			{
+				// the file was checked when validating the config, but it may have changed since
+				self.series = Some((path.clone(), read_series(path, len, traits)?));
+			}
+		}
+		for t in 0 .. traits {
+			let old: Vec<f64> = self.patches.iter().map(|(_, env)| env[t]).collect();
+			let new = match environment {
+				Environment::Random => Patch::random_env(len, &mut self.rng),
+				Environment::AlternatingHalf => Patch::alternating_env(len, 0.5, &mut self.rng),
+				Environment::AlternatingThird =>
+					Patch::alternating_env(len, 2.0 / 3.0, &mut self.rng),
+				Environment::Sine {
+					period,
+					amplitude,
+					phase_spread,
+					mean,
+				} => Patch::sine_env(len, tick, *period, *amplitude, *phase_spread, *mean),
+				Environment::RandomWalk => Patch::random_walk_env(old, &mut self.rng),
+				Environment::Constant => return Ok(()),
+				Environment::ConstantWithJumps =>
+					Patch::constant_with_jumps_env(old, &mut self.rng),
+				Environment::Correlated {
+					variance,
+					autocorrelation,
+					correlation,
+				} => Patch::correlated_env(
+					old,
+					*variance,
+					*autocorrelation,
+					*correlation,
+					&mut self.rng,
+				),
+				Environment::FromSeries { repeat, .. } => {
+					let (_, series) = self.series.as_ref().unwrap();
+					Patch::series_env(series, len, t, traits, tick, *repeat)
+				},
+			};
+			for ((_, env), new) in self.patches.iter_mut().zip(new) {
+				env[t] = new;
+			}
+		}
+		Ok(())
 	}
 
 	/// calculate amount of offspring per individual per patch
-	pub fn reproduction(&self, selection_sigma: f64) -> Vec<Vec<f64>> {
+	#[allow(clippy::too_many_arguments)]
+	pub fn reproduction(
+		&self,
+		selection_sigma: f64,
+		fitness: &FitnessFunction,
+		r_max: f64,
+		competition_sigma: Option<f64>,
+		optima: &[(f64, f64)],
+		selection_covariance: Option<&[Vec<f64>]>,
+		dioecy: Option<&Dioecy>,
+		plasticity: Option<&Plasticity>,
+	) -> Vec<Vec<f64>> {
+		let factor = selection_covariance.and_then(cholesky);
+		let cost = plasticity.map_or(0.0, |plasticity| plasticity.cost);
 		let mut reproductive_success = Vec::with_capacity(self.patches.len());
-		for (patch, env) in &self.patches {
+		for (index, (patch, env)) in self.patches.iter().enumerate() {
+			let traits: Vec<Vec<f64>> = patch
+				.iter()
+				.enumerate()
+				.map(|(i, individual)| {
+					let mut traits = self.expressed(individual, index);
+					if let Some(noise) = self.noise.get(index).and_then(|noise| noise.get(i)) {
+						traits[0] += noise;
+					}
+					traits
+				})
+				.collect();
 			let mut patch_success = Vec::with_capacity(patch.len());
-			for individual in &**patch {
-				// r(y, theta) = r_max*e^(-(theta - y)^2/(2*sigma^2)
-
-				// let offspring = ((1000.0 / (selection_sigma * (2.0 * PI).sqrt())).ln()
-				// 	- ((env - individual.phenotype()).powi(2) / (2.0 * selection_sigma.powi(2))))
-				// .exp();
-
-				let offspring = (-((env - individual.phenotype()).powi(2)
-					/ (2.0 * selection_sigma.powi(2))))
-				.exp();
-				patch_success.push(offspring);
+			for (individual, (slope, sex)) in
+				traits.iter().zip(patch.iter().map(|x| (x.slope, x.sex)))
+			{
+				// the sexes are selected towards opposite sides of the optimum
+				let (shift, sigma) = match (dioecy, sex) {
+					(Some(dioecy), Some(Sex::Male)) =>
+						(dioecy.offset, dioecy.sigma.unwrap_or(selection_sigma)),
+					(Some(dioecy), Some(Sex::Female)) => (-dioecy.offset, selection_sigma),
+					_ => (0.0, selection_sigma),
+				};
+				let deviation: Vec<f64> = individual
+					.iter()
+					.enumerate()
+					.map(|(t, y)| {
+						let theta = optima
+							.get(t)
+							.map_or(env[t], |(offset, slope)| offset + slope * env[t]);
+						theta + shift - y
+					})
+					.collect();
+				// r(y, theta) = r_max*f(theta - y), e.g. r_max*e^(-(theta - y)^2/(2*sigma^2)
+				// with a covariance matrix the distance is measured in standard deviations of the
+				// selection surface, which is widened by sigma/selection_sigma for males
+				let offspring = match &factor {
+					None => {
+						let distance = deviation.iter().map(|x| x * x).sum::<f64>().sqrt();
+						r_max * fitness.fitness(distance, sigma)
+					},
+					Some(factor) => {
+						let distance = mahalanobis(factor, &deviation).sqrt();
+						r_max * fitness.fitness(distance, sigma / selection_sigma)
+					},
+				};
+				// plastic individuals pay e^(-cost*slope^2)
+				let slope = slope.unwrap_or(0.0);
+				patch_success.push(offspring * (-cost * slope * slope).exp());
+			}
+			let phenotypes: Vec<f64> = traits.iter().map(|individual| individual[0]).collect();
+			// divide by the crowding from similar phenotypes (of the first trait) in the same patch
+			if let Some(sigma) = competition_sigma {
+				for (offspring, crowding) in
+					patch_success.iter_mut().zip(crowding(&phenotypes, sigma))
+				{
+					*offspring /= crowding;
+				}
 			}
 			reproductive_success.push(patch_success);
 		}
@@ -378,65 +1328,244 @@ impl State {
 	}
 
 	/// calculate amount of deaths per patch
-	pub fn adult_death(&mut self, gamma: f64) -> Vec<usize> {
-		let mut rng = thread_rng();
+	/// with a survival schedule every adult survives with the probability of its age class
+	pub fn adult_death(&mut self, gamma: f64, survival: Option<&[f64]>) -> Vec<usize> {
 		let mut death = Vec::with_capacity(self.patches.len());
+		self.dead.clear();
+		if let Some(survival) = survival {
+			let rng = &mut self.rng;
+			for (patch, _) in &self.patches {
+				let dead: Vec<bool> = patch
+					.iter()
+					.map(|individual| {
+						let class = (individual.age as usize).min(survival.len() - 1);
+						!rng.gen_bool(survival[class])
+					})
+					.collect();
+				death.push(dead.iter().filter(|&&dead| dead).count());
+				self.dead.push(dead);
+			}
+			return death;
+		}
 		for (patch, _) in &mut self.patches {
 			let patch_alive = Binomial::new(patch.len() as u64, gamma)
 				.unwrap()
-				.sample(&mut rng) as usize;
+				.sample(&mut self.rng) as usize;
 			death.push(patch.len() - patch_alive);
 		}
 		death
 	}
 
-	/// create new generation by cloning as many individuals in a patch as there are deaths * 2
-	pub fn density_regulation(
+	/// amount of spots every patch has left for offspring, the carrying capacity minus the
+	/// survivors, which is the amount of deaths in a full patch
+	pub fn free(&self, death: &[usize]) -> Vec<usize> {
+		izip!(&self.patches, &self.capacities, death)
+			.map(|((patch, _), capacity, death)| capacity.saturating_sub(patch.len() - death))
+			.collect()
+	}
+
+	/// calculate amount of offspring per patch as a soft and a hard share, the soft share fills
+	/// 1 - hard_selection of the free spots of its own patch, the hard share takes the rest of
+	/// the free spots and is divided among the patches according to their total fitness, the
+	/// offspring of the hard share that find no room after dispersal are lost and patches that
+	/// produce too few stay short
+	pub fn offspring(
 		&self,
+		reproductive_success: &[Vec<f64>],
+		free: &[usize],
+		hard_selection: f64,
+	) -> (Vec<usize>, Vec<usize>) {
+		let fitness: Vec<f64> = reproductive_success
+			.iter()
+			.map(|patch_success| patch_success.iter().sum())
+			.collect();
+		let fitness_sum: f64 = fitness.iter().sum();
+		if hard_selection == 0.0 || fitness_sum <= 0.0 {
+			return (free.to_vec(), vec![0; free.len()]);
+		}
+
+		let soft: Vec<usize> = free
+			.iter()
+			.map(|&free| ((1.0 - hard_selection) * free as f64).round() as usize)
+			.collect();
+		let total = free.iter().sum::<usize>() - soft.iter().sum::<usize>();
+		(soft, apportion(&fitness, total))
+	}
+
+	/// amount of offspring every patch produces when its size follows the mean reproductive
+	/// success w of its N adults, the expected size of the next generation is
+	/// Beverton-Holt: N' = w*N/(1 + (r_max - 1)*N/K)
+	/// Ricker: N' = w*N*r_max^(-N/K)
+	/// so a perfectly adapted patch settles at its carrying capacity K and a patch with w < 1
+	/// shrinks, the offspring are poisson distributed around the room the survivors leave below N'
+	/// and never exceed the free spots
+	pub fn growth(
+		&mut self,
+		reproductive_success: &[Vec<f64>],
+		death: &[usize],
+		free: &[usize],
+		r_max: f64,
+		dynamics: &PopulationDynamics,
+	) -> Vec<usize> {
+		let mut offspring = Vec::with_capacity(self.patches.len());
+		for ((patch, _), patch_success, death, &free, &capacity) in izip!(
+			&self.patches,
+			reproductive_success,
+			death,
+			free,
+			&self.capacities
+		) {
+			let n = patch.len() as f64;
+			let density = n / capacity as f64;
+			let w = match patch.len() {
+				0 => 0.0,
+				len => patch_success.iter().sum::<f64>() / len as f64,
+			};
+			let expected = match dynamics {
+				PopulationDynamics::BevertonHolt => w * n / (1.0 + (r_max - 1.0) * density),
+				PopulationDynamics::Ricker => w * n * r_max.powf(-density),
+			};
+			let mean = expected - (patch.len() - death) as f64;
+			let born = match Poisson::new(mean) {
+				Ok(poisson) if mean > 0.0 => poisson.sample(&mut self.rng) as usize,
+				_ => 0,
+			};
+			offspring.push(born.min(free));
+		}
+		offspring
+	}
+
+	/// create new generation by cloning as many individuals in a patch as there are offspring * 2
+	/// with separate sexes every mother is followed by a father, and a patch without either sex
+	/// produces nothing
+	pub fn density_regulation(
+		&mut self,
 		reproductive_success: Vec<Vec<f64>>,
-		death: &Vec<usize>,
+		offspring: &[usize],
 		diploid: bool,
+		dioecious: bool,
 	) -> Vec<Patch> {
 		let times = if diploid { 2 } else { 1 };
+		// parents are drawn proportional to their success, or uniformly if nobody succeeds
+		let alias = |success: Vec<f64>| match success.iter().sum::<f64>() > 0.0 {
+			true => WeightedAliasIndex::new(success).unwrap(),
+			false => WeightedAliasIndex::new(vec![1.0; success.len()]).unwrap(),
+		};
 		let mut new_generation = Vec::with_capacity(self.patches.len());
-		for ((patch, _), patch_success, patch_death) in
-			izip!(&self.patches, reproductive_success, death)
+		for ((patch, _), patch_success, &patch_offspring) in
+			izip!(&self.patches, reproductive_success, offspring)
 		{
-			let distr = if patch_success.iter().sum::<f64>() > 0.0 {
-				WeightedAliasIndex::new(patch_success).unwrap()
-			} else {
-				WeightedAliasIndex::new(vec![1.0; patch_success.len()]).unwrap()
-			};
+			// an extinct patch has nobody to reproduce
+			if patch.is_empty() {
+				new_generation.push(Patch::new(Vec::new()));
+				continue;
+			}
+			if diploid && dioecious {
+				let (mothers, fathers): (Vec<usize>, Vec<usize>) =
+					(0 .. patch.len()).partition(|&i| patch[i].sex == Some(Sex::Female));
+				if mothers.is_empty() || fathers.is_empty() {
+					new_generation.push(Patch::new(Vec::new()));
+					continue;
+				}
+				let mother = alias(mothers.iter().map(|&i| patch_success[i]).collect());
+				let father = alias(fathers.iter().map(|&i| patch_success[i]).collect());
+				let mut individuals = Vec::with_capacity(2 * patch_offspring);
+				for _ in 0 .. patch_offspring {
+					individuals.push(patch[mothers[mother.sample(&mut self.rng)]].offspring());
+					individuals.push(patch[fathers[father.sample(&mut self.rng)]].offspring());
+				}
+				new_generation.push(Patch::new(individuals));
+				continue;
+			}
 			new_generation.push(Patch::new(
-				distr
-					.sample_iter(thread_rng())
-					.take(times * patch_death)
-					.map(|index| patch[index].clone())
+				alias(patch_success)
+					.sample_iter(&mut self.rng)
+					.take(times * patch_offspring)
+					.map(|index| patch[index].offspring())
 					.collect(),
 			));
 		}
 		new_generation
 	}
 
+	/// pair every parent with a mate from the partners drawn in its patch, itself with probability
+	/// selfing, otherwise a partner whose expressed phenotype lies at distance d is preferred with
+	/// weight e^(-assortative_mating*d^2), so similar individuals mate more often
+	pub fn mating(
+		&mut self,
+		mut new_generation: Vec<Patch>,
+		selfing: f64,
+		assortative_mating: f64,
+	) -> Vec<Patch> {
+		if selfing == 0.0 && assortative_mating == 0.0 {
+			return new_generation;
+		}
+		for (index, patch) in new_generation.iter_mut().enumerate() {
+			let phenotypes: Vec<f64> = patch
+				.iter()
+				.map(|individual| self.expressed(individual, index)[0])
+				.collect();
+			// the partners that were drawn for the parent in front of them
+			let partners: Vec<Individual> = patch.iter().skip(1).step_by(2).cloned().collect();
+			let preference = |parent: usize, partner: usize| {
+				let distance = phenotypes[parent] - phenotypes[2 * partner + 1];
+				(-assortative_mating * distance * distance).exp()
+			};
+			for i in 0 .. partners.len() {
+				let parent = 2 * i;
+				if self.rng.gen_bool(selfing) {
+					patch[parent + 1] = patch[parent].clone();
+					continue;
+				}
+				if assortative_mating == 0.0 {
+					continue;
+				}
+				// try random partners first and only weigh all of them if nobody is accepted
+				let rng = &mut self.rng;
+				let chosen = (0 .. 100).find_map(|_| {
+					let partner = gen_index(rng, partners.len());
+					rng.gen_bool(preference(parent, partner)).then_some(partner)
+				});
+				let partner = match chosen {
+					Some(partner) => partner,
+					None => {
+						let weights: Vec<f64> = (0 .. partners.len())
+							.map(|partner| preference(parent, partner))
+							.collect();
+						match WeightedIndex::new(&weights) {
+							Ok(distr) => distr.sample(&mut self.rng),
+							Err(_) => gen_index(&mut self.rng, partners.len()),
+						}
+					},
+				};
+				patch[parent + 1] = partners[partner].clone();
+			}
+		}
+		new_generation
+	}
+
 	/// produce gametes with recombination and then join every two gametes together for every patch
 	/// results in new generation with as many individuals as deaths in the patch
-	pub fn recombination(&self, mut new_generation: Vec<Patch>, rec: f64) -> Vec<Patch> {
-		let k = self.patches[0].0[0].len() / 2;
-		// rec = 1-(1-locus_rec)^(k-1)
-		let locus_rec = if (rec == 0.0) {
-			0.0
-		} else {
-			1.0 - (1.0 / ((k - 1) as f64) * (1.0 - rec).ln()).exp()
+	pub fn recombination(&mut self, mut new_generation: Vec<Patch>, rec: f64) -> Vec<Patch> {
+		let k = self.loci();
+		let intervals = match &self.recombination_map {
+			Some(map) => map.clone(),
+			// rec = 1-(1-locus_rec)^(k-1)
+			None if rec == 0.0 || k < 2 => vec![0.0; k.saturating_sub(1)],
+			None => vec![1.0 - (1.0 / ((k - 1) as f64) * (1.0 - rec).ln()).exp(); k - 1],
 		};
-		let mut rng = thread_rng();
-		let distr = Bernoulli::new(locus_rec).expect(&locus_rec.to_string());
+		let distr: Vec<Bernoulli> = intervals
+			.into_iter()
+			.map(|locus_rec| Bernoulli::new(locus_rec).unwrap())
+			.collect();
 		let swapped = Bernoulli::new(0.5).unwrap();
 		for patch in &mut new_generation {
 			for individual in &mut **patch {
 				let (loci1, loci2) = individual.split_at_mut(k);
-				let mut swapped = swapped.sample(&mut rng);
-				for (locus1, locus2) in loci1.iter_mut().zip(&*loci2) {
-					if distr.sample(&mut rng) {
+				let mut swapped = swapped.sample(&mut self.rng);
+				for (i, (locus1, locus2)) in loci1.iter_mut().zip(&*loci2).enumerate() {
+					// crossover in the interval before this locus
+					if i != 0 && distr[i - 1].sample(&mut self.rng) {
 						swapped = !swapped;
 					}
 					if swapped {
@@ -448,34 +1577,67 @@ impl State {
 			for i in 0 .. len {
 				unsafe {
 					let individual = &mut *(patch.get_unchecked_mut(i) as *mut Individual);
-					individual[.. k].copy_from_slice(&patch[2 * i][.. k]);
+					// the first gamete of the first pair is already in place
+					if i != 0 {
+						individual[.. k].copy_from_slice(&patch[2 * i][.. k]);
+					}
 					individual[k ..].copy_from_slice(&patch[(2 * i) + 1][.. k]);
+					// a newborn whatever the slot it is built in
+					individual.age = 0;
+					individual.parent_age =
+						(patch[2 * i].parent_age + patch[(2 * i) + 1].parent_age) / 2.0;
+					// the modifier locus is inherited from either parent
+					individual.dispersal = patch[(2 * i) + gen_index(&mut self.rng, 2)].dispersal;
+					individual.mutation = patch[(2 * i) + gen_index(&mut self.rng, 2)].mutation;
+					individual.slope = patch[(2 * i) + gen_index(&mut self.rng, 2)].slope;
+					// every marker is unlinked and comes from either haplotype of each parent
+					let markers = patch[2 * i].markers.len() / 2;
+					let gametes: Vec<i64> = [2 * i, (2 * i) + 1]
+						.iter()
+						.flat_map(|&parent| {
+							(0 .. markers)
+								.map(|l| {
+									patch[parent].markers[l + markers * gen_index(&mut self.rng, 2)]
+								})
+								.collect::<Vec<_>>()
+						})
+						.collect();
+					individual.markers = gametes;
+					// the offspring of separate sexes is a son or a daughter with equal probability
+					individual.sex = individual.sex.map(|_| Sex::random(&mut self.rng));
 				}
 			}
-			patch.resize(
-				len,
-				Individual {
-					loci: Default::default(),
-				},
-			)
+			patch.resize(len, Default::default())
 		}
 		new_generation
 	}
 
 	/// determine for every individual in the new generation if it will disperse
-	/// then shuffle all the dispersing individuals around
-	pub fn dispersal(mut new_generation: Vec<Patch>, m: f64) -> Vec<Patch> {
-		let mut rng = thread_rng();
-		let distr = Bernoulli::new(m).unwrap();
+	/// then move all the dispersing individuals around according to the dispersal topology
+	pub fn dispersal(
+		&mut self,
+		new_generation: Vec<Patch>,
+		m: f64,
+		dispersal: &Dispersal,
+	) -> Vec<Patch> {
+		match dispersal.matrix(new_generation.len()) {
+			None => self.island_dispersal(new_generation, m),
+			Some(matrix) => self.matrix_dispersal(new_generation, m, &matrix),
+		}
+	}
+
+	/// shuffle all the dispersing individuals around
+	fn island_dispersal(&mut self, mut new_generation: Vec<Patch>, m: f64) -> Vec<Patch> {
+		let rng = &mut self.rng;
 		let mut pool: Vec<_> = new_generation
 			.iter_mut()
 			.map(|patch| &mut **patch)
 			.flatten()
-			.filter(|_| distr.sample(&mut rng))
+			.filter(|individual| rng.gen_bool(individual.dispersal.unwrap_or(m)))
 			.collect();
 		for i in (1 .. pool.len()).rev() {
 			let pa = ptr::addr_of_mut!(*pool[i]);
-			let pb = ptr::addr_of_mut!(*pool[gen_index(&mut thread_rng(), i + 1)]);
+			let pb = ptr::addr_of_mut!(*pool[gen_index(rng, i + 1)]);
 			unsafe {
 				ptr::swap(pa, pb);
 			}
@@ -483,46 +1645,333 @@ impl State {
 		new_generation
 	}
 
+	/// move every disperser into a spot left by another disperser, the destination patch is chosen
+	/// proportional to the migration matrix times the amount of spots that are still free there, a
+	/// disperser without a free spot among its neighbours stays home and the patch it crowds out
+	/// loses the surplus when merging generations
+	fn matrix_dispersal(
+		&mut self,
+		mut new_generation: Vec<Patch>,
+		m: f64,
+		matrix: &[Vec<f64>],
+	) -> Vec<Patch> {
+		let mut free = Vec::with_capacity(new_generation.len());
+		let mut pool = Vec::new();
+		for (index, patch) in new_generation.iter_mut().enumerate() {
+			let (leave, stay): (Vec<_>, Vec<_>) = patch
+				.individuals
+				.drain(..)
+				.partition(|individual| self.rng.gen_bool(individual.dispersal.unwrap_or(m)));
+			patch.individuals = stay;
+			free.push(leave.len());
+			pool.extend(leave.into_iter().map(|individual| (index, individual)));
+		}
+
+		pool.shuffle(&mut self.rng);
+		for (origin, individual) in pool {
+			let weights: Vec<f64> = matrix[origin]
+				.iter()
+				.zip(&free)
+				.map(|(rate, &free)| rate * free as f64)
+				.collect();
+			let destination = match WeightedIndex::new(&weights) {
+				Ok(distr) => distr.sample(&mut self.rng),
+				// nowhere left to go according to the topology, so stay home
+				Err(_) => origin,
+			};
+			free[destination] = free[destination].saturating_sub(1);
+			new_generation[destination].individuals.push(individual);
+		}
+		new_generation
+	}
+
+	/// let every disperser settle in a patch drawn from the migration matrix, or any patch under
+	/// the global topology, regardless of the room there, so the patches change in size and empty
+	/// patches are recolonised, the offspring that do not fit are lost when merging generations
+	pub fn colonisation(
+		&mut self,
+		mut new_generation: Vec<Patch>,
+		m: f64,
+		dispersal: &Dispersal,
+	) -> Vec<Patch> {
+		let len = new_generation.len();
+		let matrix = dispersal.matrix(len);
+		let mut pool = Vec::new();
+		for (index, patch) in new_generation.iter_mut().enumerate() {
+			let (leave, stay): (Vec<_>, Vec<_>) = patch
+				.individuals
+				.drain(..)
+				.partition(|individual| self.rng.gen_bool(individual.dispersal.unwrap_or(m)));
+			patch.individuals = stay;
+			pool.extend(leave.into_iter().map(|individual| (index, individual)));
+		}
+
+		for (origin, individual) in pool {
+			let destination = match &matrix {
+				// nowhere to go according to the topology, so stay home
+				Some(matrix) if matrix[origin].iter().sum::<f64>() <= 0.0 => origin,
+				Some(matrix) => WeightedIndex::new(&matrix[origin])
+					.unwrap()
+					.sample(&mut self.rng),
+				None => gen_index(&mut self.rng, len),
+			};
+			new_generation[destination].individuals.push(individual);
+		}
+		// the immigrants compete for the free spots on equal terms with the locals
+		for patch in &mut new_generation {
+			patch.shuffle(&mut self.rng);
+		}
+		new_generation
+	}
+
 	/// mutate the value of every locus in every individual in every patch of the new generation
+	/// an individual mutates with its own mutation probability if it carries a mutator
 	pub fn mutation(
+		&mut self,
 		mut new_generation: Vec<Patch>,
 		mutation_mu: f64,
 		mutation_sigma: f64,
 		mutation_step: f64,
+		model: &MutationModel,
+		rates: Option<&[f64]>,
 	) -> Vec<Patch> {
-		let mut rng = thread_rng();
+		let distr: Option<Vec<Bernoulli>> = rates.map(|rates| {
+			rates
+				.iter()
+				.map(|&rate| Bernoulli::new(rate).unwrap())
+				.collect()
+		});
+		let up_down = Bernoulli::new(0.5).unwrap();
+		let normal = Normal::new(0.0, mutation_sigma).unwrap();
+		let discrete =
+			|rng: &mut ChaCha8Rng| mutation_step * (normal.sample(rng) / mutation_step).round();
 
+		for patch in &mut new_generation {
+			for individual in &mut **patch {
+				let mutation = Bernoulli::new(individual.mutation.unwrap_or(mutation_mu)).unwrap();
+				// both haplotypes share the per locus rates
+				for (i, locus) in individual.iter_mut().enumerate() {
+					let distr = match &distr {
+						Some(distr) => &distr[i % distr.len()],
+						None => &mutation,
+					};
+					if !distr.sample(&mut self.rng) {
+						continue;
+					}
+					*locus = match *model {
+						MutationModel::Gaussian => *locus + normal.sample(&mut self.rng),
+						MutationModel::DiscreteGaussian => *locus + discrete(&mut self.rng),
+						MutationModel::Stepwise => match up_down.sample(&mut self.rng) {
+							true => *locus + mutation_step,
+							false => *locus - mutation_step,
+						},
+						MutationModel::HouseOfCards => normal.sample(&mut self.rng),
+						MutationModel::Bounded { min, max } =>
+							reflect(*locus + discrete(&mut self.rng), min, max),
+					}
+				}
+			}
+		}
+		new_generation
+	}
+
+	/// mutate the heritable dispersal probability of every individual in the new generation with
+	/// the same probability as a locus, an individual without a modifier starts from m
+	pub fn dispersal_mutation(
+		&mut self,
+		mut new_generation: Vec<Patch>,
+		mutation_mu: f64,
+		dispersal_sigma: f64,
+		m: f64,
+	) -> Vec<Patch> {
 		let distr = Bernoulli::new(mutation_mu).unwrap();
-		// fixed
-		// let up_down = Bernoulli::new(0.5).unwrap();
-		// normal
-		let up_down = Normal::new(0.0, mutation_sigma).unwrap();
+		let normal = Normal::new(0.0, dispersal_sigma).unwrap();
+		for patch in &mut new_generation {
+			for individual in &mut **patch {
+				let mut dispersal = individual.dispersal.unwrap_or(m);
+				if distr.sample(&mut self.rng) {
+					dispersal = reflect(dispersal + normal.sample(&mut self.rng), 0.0, 1.0);
+				}
+				individual.dispersal = Some(dispersal);
+			}
+		}
+		new_generation
+	}
+
+	/// mutate the mutator of every individual in the new generation with its own mutation
+	/// probability, an individual without a mutator starts from mutation_mu
+	pub fn mutator_mutation(
+		&mut self,
+		mut new_generation: Vec<Patch>,
+		mutation_mu: f64,
+		mutator_sigma: f64,
+	) -> Vec<Patch> {
+		let normal = Normal::new(0.0, mutator_sigma).unwrap();
+		for patch in &mut new_generation {
+			for individual in &mut **patch {
+				let mut mutation = individual.mutation.unwrap_or(mutation_mu);
+				if self.rng.gen_bool(mutation) {
+					mutation = reflect(mutation + normal.sample(&mut self.rng), 0.0, 1.0);
+				}
+				individual.mutation = Some(mutation);
+			}
+		}
+		new_generation
+	}
 
+	/// mutate every neutral marker allele in the new generation with the marker mutation
+	/// probability
+	pub fn marker_mutation(
+		&mut self,
+		mut new_generation: Vec<Patch>,
+		markers: &Markers,
+	) -> Vec<Patch> {
+		let mutation = Bernoulli::new(markers.mu).unwrap();
 		for patch in &mut new_generation {
 			for individual in &mut **patch {
-				for locus in &mut **individual {
-					if distr.sample(&mut rng) {
-						// fixed
-						// *locus +=
-						// 	2.0 * mutation_step * (up_down.sample(&mut rng) as i32 as f64 - 0.5);
-						// normal
-						*locus += mutation_step * (up_down.sample(&mut rng) / mutation_step).round()
+				for allele in &mut individual.markers {
+					if !mutation.sample(&mut self.rng) {
+						continue;
 					}
+					*allele = match markers.model {
+						MarkerModel::InfiniteAlleles => {
+							self.alleles += 1;
+							self.alleles
+						},
+						MarkerModel::Stepwise => match self.rng.gen_bool(0.5) {
+							true => *allele + 1,
+							false => *allele - 1,
+						},
+					};
 				}
 			}
 		}
 		new_generation
 	}
 
+	/// mutate the reaction norm slope of every individual in the new generation with the same
+	/// probability as a locus, an individual without a slope starts from 0
+	pub fn plasticity_mutation(
+		&mut self,
+		mut new_generation: Vec<Patch>,
+		mutation_mu: f64,
+		plasticity_sigma: f64,
+	) -> Vec<Patch> {
+		let distr = Bernoulli::new(mutation_mu).unwrap();
+		let normal = Normal::new(0.0, plasticity_sigma).unwrap();
+		for patch in &mut new_generation {
+			for individual in &mut **patch {
+				let mut slope = individual.slope.unwrap_or(0.0);
+				if distr.sample(&mut self.rng) {
+					slope += normal.sample(&mut self.rng);
+				}
+				individual.slope = Some(slope);
+			}
+		}
+		new_generation
+	}
+
+	/// let the seed bank of every patch decay and germinate and let part of the new generation
+	/// become dormant, the germinated and the active offspring then compete for the free spots of
+	/// the patch while every dormant offspring joins the seed bank, so germinating seeds also
+	/// refill patches that have shrunk or gone extinct
+	pub fn dormancy(
+		&mut self,
+		mut new_generation: Vec<Patch>,
+		seed_bank: &SeedBank,
+		free: &[usize],
+	) -> Vec<Patch> {
+		let dormancy = Bernoulli::new(seed_bank.dormancy).unwrap();
+		let germination = Bernoulli::new(seed_bank.germination).unwrap();
+		let decay = Bernoulli::new(seed_bank.decay).unwrap();
+		self.dormant
+			.resize(new_generation.len(), Patch::new(Vec::new()));
+		for (patch, pool, &spots) in izip!(&mut new_generation, &mut self.dormant, free) {
+			let rng = &mut self.rng;
+			pool.individuals.retain(|_| !decay.sample(rng));
+			// the time spent dormant counts towards the generation time
+			for seed in &mut **pool {
+				seed.age = seed.age.saturating_add(1);
+			}
+			let (mut candidates, stay): (Vec<_>, Vec<_>) = pool
+				.individuals
+				.drain(..)
+				.partition(|_| germination.sample(rng));
+			pool.individuals = stay;
+
+			let (seeds, active): (Vec<_>, Vec<_>) = patch
+				.individuals
+				.drain(..)
+				.partition(|_| dormancy.sample(rng));
+			candidates.extend(active);
+			candidates.shuffle(rng);
+			candidates.truncate(spots);
+			patch.individuals = candidates;
+			pool.individuals.extend(seeds);
+		}
+		new_generation
+	}
+
 	/// replace the old generation with the new one
-	fn update(&mut self, new_generation: Vec<Patch>, death: Vec<usize>) {
-		for ((patch, _), new, death) in izip!(&mut self.patches, new_generation, death) {
-			patch.shuffle(&mut thread_rng());
-			let len = patch.len() - death;
-			patch.resize(len, Default::default());
+	/// the survivors age a tick and the offspring start at age 0, no patch grows beyond its
+	/// carrying capacity
+	fn update(&mut self, mut new_generation: Vec<Patch>, death: Vec<usize>) {
+		for (index, ((patch, _), new, death, capacity)) in izip!(
+			&mut self.patches,
+			&mut new_generation,
+			death,
+			&self.capacities
+		)
+		.enumerate()
+		{
+			match self.dead.get(index) {
+				Some(dead) => {
+					let mut dead = dead.iter();
+					patch.individuals.retain(|_| !dead.next().unwrap());
+				},
+				None => {
+					patch.shuffle(&mut self.rng);
+					let len = patch.len() - death;
+					patch.resize(len, Default::default());
+				},
+			}
+			new.individuals
+				.truncate(capacity.saturating_sub(patch.len()));
+			for individual in &mut **patch {
+				individual.age = individual.age.saturating_add(1);
+			}
+		}
+
+		// the offspring remember the age of their parents, and their own age holds the ticks they
+		// spent dormant
+		let births: usize = new_generation.iter().map(|patch| patch.len()).sum();
+		let parent_age: f64 = new_generation
+			.iter()
+			.flat_map(|patch| patch.iter())
+			.map(|individual| individual.parent_age + individual.age as f64)
+			.sum();
+		self.generation_time = (births > 0).then(|| parent_age / births as f64 + 1.0);
+
+		for ((patch, _), mut new) in self.patches.iter_mut().zip(new_generation) {
+			for individual in &mut *new {
+				individual.age = 0;
+			}
 			patch.extend(new);
 		}
 	}
+
+	/// amount of individuals of every age, up to the oldest individual
+	pub fn age_distribution(&self) -> Vec<usize> {
+		let mut ages = Vec::new();
+		for individual in self.patches.iter().flat_map(|(patch, _)| patch.iter()) {
+			let age = individual.age as usize;
+			if ages.len() <= age {
+				ages.resize(age + 1, 0);
+			}
+			ages[age] += 1;
+		}
+		ages
+	}
 }
 
 pub fn init(init_config: InitConfig, env: Environment) -> Result<State, &'static str> {
@@ -534,63 +1983,355 @@ pub fn init(init_config: InitConfig, env: Environment) -> Result<State, &'static
 		false => init_config.loci,
 	};
 
-	let patch_size = individuals / patches;
-	let p = match init_config.kind {
-		InitialPopulation::UniformI => Patch::uniform_i(patches, patch_size, loci),
-		InitialPopulation::UniformP => Patch::uniform_p(patches, patch_size, loci),
-		InitialPopulation::Uniform => Patch::uniform(patches, patch_size, loci),
-		InitialPopulation::ConstantI => Patch::constant_i(patches, patch_size, loci),
-		InitialPopulation::ConstantP => Patch::constant_p(patches, patch_size, loci),
-		InitialPopulation::Constant => Patch::constant(patches, patch_size, loci),
-		InitialPopulation::NormalI => Patch::normal_i(patches, patch_size, loci),
-		InitialPopulation::NormalP => Patch::normal_p(patches, patch_size, loci),
-		InitialPopulation::Normal => Patch::normal(patches, patch_size, loci),
-		InitialPopulation::AlternatingHalf => Patch::alternating(patches, patch_size, loci, 0.5),
+	if init_config.traits == 0 || init_config.traits > init_config.loci {
+		return Err("Every trait needs at least one locus.");
+	}
+	if let Some(map) = &init_config.recombination_map {
+		if map.len() + 1 != init_config.loci || map.iter().any(|p| !(0.0 ..= 0.5).contains(p)) {
+			return Err(
+				"The recombination map needs a probability between 0 and 0.5 for every pair of \
+				 adjacent loci.",
+			);
+		}
+	}
+
+	let capacities = init_config.capacities.resolve(patches, individuals)?;
+
+	let seed = init_config.seed.unwrap_or_else(|| thread_rng().gen());
+	let mut rng = ChaCha8Rng::seed_from_u64(seed);
+
+	// every patch starts out full, the patches are made as large as the largest one
+	let patch_size = capacities.iter().copied().max().unwrap_or(0);
+	let mut p = match init_config.kind {
+		InitialPopulation::UniformI => Patch::uniform_i(patches, patch_size, loci, &mut rng),
+		InitialPopulation::UniformP => Patch::uniform_p(patches, patch_size, loci, &mut rng),
+		InitialPopulation::Uniform => Patch::uniform(patches, patch_size, loci, &mut rng),
+		InitialPopulation::ConstantI => Patch::constant_i(patches, patch_size, loci, &mut rng),
+		InitialPopulation::ConstantP => Patch::constant_p(patches, patch_size, loci, &mut rng),
+		InitialPopulation::Constant => Patch::constant(patches, patch_size, loci, &mut rng),
+		InitialPopulation::NormalI => Patch::normal_i(patches, patch_size, loci, &mut rng),
+		InitialPopulation::NormalP => Patch::normal_p(patches, patch_size, loci, &mut rng),
+		InitialPopulation::Normal => Patch::normal(patches, patch_size, loci, &mut rng),
+		InitialPopulation::AlternatingHalf =>
+			Patch::alternating(patches, patch_size, loci, 0.5, &mut rng),
 		InitialPopulation::AlternatingThird =>
-			Patch::alternating(patches, patch_size, loci, 2.0 / 3.0),
+			Patch::alternating(patches, patch_size, loci, 2.0 / 3.0, &mut rng),
+	};
+	for (patch, &capacity) in p.iter_mut().zip(&capacities) {
+		patch.individuals.truncate(capacity);
 	}
-	.into_iter();
 
-	let e = match env {
-		Environment::Random => Patch::random_env(patches),
-		Environment::AlternatingHalf => Patch::alternating_env(patches, 0.5),
-		Environment::AlternatingThird => Patch::alternating_env(patches, 2.0 / 3.0),
-		Environment::SineSlow => Patch::sine_env(patches, 0, 70_000.0),
-		Environment::SineMedium => Patch::sine_env(patches, 0, 20_000.0),
-		Environment::SineFast => Patch::sine_env(patches, 0, 7_000.0),
-		Environment::RandomWalk => Patch::random_walk_env(vec![0.0; patches]),
-		Environment::Constant | Environment::ConstantWithJumps => Uniform::new(-1.0, 1.0)
-			.sample_iter(&mut thread_rng())
-			.take(patches)
-			.collect(),
+	let traits = init_config.traits;
+	let series = match &env {
+		Environment::FromSeries { path, .. } =>
+			Some((path.clone(), read_series(path, patches, traits)?)),
+		_ => None,
+	};
+
+	// every trait has its own component of the environment
+	let mut e = vec![Vec::with_capacity(traits); patches];
+	for t in 0 .. traits {
+		let component = match &env {
+			Environment::Random => Patch::random_env(patches, &mut rng),
+			Environment::AlternatingHalf => Patch::alternating_env(patches, 0.5, &mut rng),
+			Environment::AlternatingThird => Patch::alternating_env(patches, 2.0 / 3.0, &mut rng),
+			Environment::Sine {
+				period,
+				amplitude,
+				phase_spread,
+				mean,
+			} => Patch::sine_env(patches, 0, *period, *amplitude, *phase_spread, *mean),
+			Environment::RandomWalk => Patch::random_walk_env(vec![0.0; patches], &mut rng),
+			Environment::Constant | Environment::ConstantWithJumps => Uniform::new(-1.0, 1.0)
+				.sample_iter(&mut rng)
+				.take(patches)
+				.collect(),
+			// without autocorrelation this draws from the stationary distribution
+			Environment::Correlated {
+				variance,
+				correlation,
+				..
+			} => Patch::correlated_env(vec![0.0; patches], *variance, 0.0, *correlation, &mut rng),
+			Environment::FromSeries { repeat, .. } =>
+				Patch::series_env(&series.as_ref().unwrap().1, patches, t, traits, 0, *repeat),
+		};
+		for (env, x) in e.iter_mut().zip(component) {
+			env.push(x);
+		}
 	}
-	.into_iter();
 
 	let state = State {
-		tick:    0,
-		patches: p.zip(e).collect(),
+		tick: 0,
+		patches: p.into_iter().zip(e).collect(),
 		diploid: init_config.diploid,
+		genotype_map: init_config.genotype_map,
+		traits,
+		capacities,
+		recombination_map: init_config.recombination_map,
+		cues: Vec::new(),
+		heritability: None,
+		generation_time: None,
+		dormant: Vec::new(),
+		fst: None,
+		qst: None,
+		seed,
+		rng,
+		series,
+		history: VecDeque::new(),
+		noise: Vec::new(),
+		dead: Vec::new(),
+		success: Vec::new(),
+		alleles: 0,
 	};
 
 	Ok(state)
 }
 
-pub fn step(state: &mut State, config: &Config) {
-	state.environment(&config.environment, state.tick);
-	let reproductive_success = state.reproduction(config.selection_sigma);
-	let death = state.adult_death(config.gamma);
-	let mut new_generation = state.density_regulation(reproductive_success, &death, state.diploid);
+/// advance the simulation by a tick, fails if the environment cannot be updated
+pub fn step(state: &mut State, config: &Config) -> Result<(), &'static str> {
+	state.environment(&config.environment, state.tick)?;
+	state.sexes(config.dioecy.as_ref());
+	state.markers(config.markers.as_ref());
+	state.dispersal_modifier(config.dispersal_sigma);
+	state.mutator(config.mutator_sigma);
+	state.cue(config.plasticity.as_ref());
+	state.development(config.developmental_variance);
+	let reproductive_success = state.reproduction(
+		config.selection_sigma,
+		&config.fitness,
+		config.r_max,
+		config.competition_sigma,
+		&config.optima,
+		config.selection_covariance.as_deref(),
+		config.dioecy.as_ref(),
+		config.plasticity.as_ref(),
+	);
+	state.success = reproductive_success.clone();
+	let death = state.adult_death(config.gamma, config.survival.as_deref());
+	let free = state.free(&death);
+	let (soft, hard) = match &config.population_dynamics {
+		Some(dynamics) => (
+			vec![0; free.len()],
+			state.growth(&reproductive_success, &death, &free, config.r_max, dynamics),
+		),
+		None => state.offspring(&reproductive_success, &free, config.hard_selection),
+	};
+	let offspring: Vec<usize> = soft
+		.iter()
+		.zip(&hard)
+		.map(|(soft, hard)| soft + hard)
+		.collect();
+	let mut new_generation = state.density_regulation(
+		reproductive_success,
+		&offspring,
+		state.diploid,
+		config.dioecy.is_some(),
+	);
 	if state.diploid {
+		new_generation = state.mating(new_generation, config.selfing, config.assortative_mating);
 		new_generation = state.recombination(new_generation, config.rec);
 	}
-	new_generation = State::dispersal(new_generation, config.m);
-	new_generation = State::mutation(
+	// the soft share keeps the patch sizes fixed, the hard share lets them follow productivity
+	let hard_generation: Vec<Patch> = new_generation
+		.iter_mut()
+		.zip(&soft)
+		.map(|(patch, &soft)| Patch::new(patch.individuals.split_off(soft.min(patch.len()))))
+		.collect();
+	new_generation = state.dispersal(new_generation, config.m, &config.dispersal);
+	if hard.iter().any(|&hard| hard > 0) {
+		let hard_generation = state.colonisation(hard_generation, config.m, &config.dispersal);
+		for (patch, hard) in new_generation.iter_mut().zip(hard_generation) {
+			patch.individuals.extend(hard.individuals);
+			patch.shuffle(&mut state.rng);
+		}
+	}
+	new_generation = state.mutation(
 		new_generation,
 		config.mutation_mu,
 		config.mutation_sigma,
 		config.mutation_step,
+		&config.mutation_model,
+		config.mutation_rates.as_deref(),
 	);
+	if let Some(dispersal_sigma) = config.dispersal_sigma {
+		new_generation = state.dispersal_mutation(
+			new_generation,
+			config.mutation_mu,
+			dispersal_sigma,
+			config.m,
+		);
+	}
+	if let Some(markers) = &config.markers {
+		new_generation = state.marker_mutation(new_generation, markers);
+	}
+	if let Some(mutator_sigma) = config.mutator_sigma {
+		new_generation = state.mutator_mutation(new_generation, config.mutation_mu, mutator_sigma);
+	}
+	if let Some(plasticity) = &config.plasticity {
+		new_generation =
+			state.plasticity_mutation(new_generation, config.mutation_mu, plasticity.sigma);
+	}
+	match &config.seed_bank {
+		Some(seed_bank) => new_generation = state.dormancy(new_generation, seed_bank, &free),
+		None => state.dormant.clear(),
+	}
 	state.update(new_generation, death);
+	state.fst = stats::fst(state);
+	state.qst = stats::qst(state);
+	Ok(())
+}
+
+/// location of an environment series in the series directory, absolute paths and paths that leave
+/// the directory are refused so no other file on the server can be read
+pub fn series_path(name: &str) -> Result<PathBuf, &'static str> {
+	let path = Path::new(name);
+	let inside = path
+		.components()
+		.all(|component| matches!(component, Component::Normal(_)));
+	match !name.is_empty() && inside {
+		true => Ok(Path::new(SERIES_DIR).join(path)),
+		false => Err("The environment series must be a file inside the series directory."),
+	}
+}
+
+/// read a table of environmental optima from a csv file in the series directory
+pub fn read_series(
+	name: &str,
+	patches: usize,
+	traits: usize,
+) -> Result<Vec<Vec<f64>>, &'static str> {
+	let text = std::fs::read_to_string(series_path(name)?)
+		.map_err(|_| "The environment series file could not be read.")?;
+	parse_series(&text, patches, traits)
+}
+
+/// parse a table of environmental optima with a row per tick and either a column per patch, a
+/// single column shared by all patches or a column per patch and trait, a header line is skipped
+pub fn parse_series(
+	text: &str,
+	patches: usize,
+	traits: usize,
+) -> Result<Vec<Vec<f64>>, &'static str> {
+	let parse = |line: &str| {
+		line.split(',')
+			.map(|x| x.trim().parse::<f64>().ok())
+			.collect::<Option<Vec<_>>>()
+	};
+
+	let mut lines = text
+		.lines()
+		.filter(|line| !line.trim().is_empty())
+		.peekable();
+	if matches!(lines.peek(), Some(line) if parse(line).is_none()) {
+		lines.next();
+	}
+
+	let series = lines
+		.map(parse)
+		.collect::<Option<Vec<_>>>()
+		.ok_or("The environment series may only contain numbers.")?;
+	if series.is_empty() {
+		return Err("The environment series is empty.");
+	}
+	if series
+		.iter()
+		.any(|row| ![1, patches, patches * traits].contains(&row.len()))
+	{
+		return Err(
+			"The environment series must have a single column, one for every patch or one for \
+			 every patch and trait.",
+		);
+	}
+	Ok(series)
+}
+
+/// mean competition kernel e^(-(y - z)^2/(2*sigma^2)) between every phenotype y and all phenotypes
+/// z in the patch, pairs more than 6 kernel widths apart are ignored
+fn crowding(phenotypes: &[f64], sigma: f64) -> Vec<f64> {
+	let mut order: Vec<usize> = (0 .. phenotypes.len()).collect();
+	order.sort_by(|&a, &b| phenotypes[a].total_cmp(&phenotypes[b]));
+	let sorted: Vec<f64> = order.iter().map(|&index| phenotypes[index]).collect();
+
+	let mut crowding = vec![0.0; phenotypes.len()];
+	let mut start = 0;
+	for (&index, &y) in order.iter().zip(&sorted) {
+		while sorted[start] < y - 6.0 * sigma {
+			start += 1;
+		}
+		let total: f64 = sorted[start ..]
+			.iter()
+			.take_while(|&&z| z <= y + 6.0 * sigma)
+			.map(|z| (-((y - z).powi(2) / (2.0 * sigma.powi(2)))).exp())
+			.sum();
+		crowding[index] = total / phenotypes.len() as f64;
+	}
+	crowding
+}
+
+/// lower triangular cholesky factor of a symmetric matrix, None if it is not positive definite
+fn cholesky(matrix: &[Vec<f64>]) -> Option<Vec<Vec<f64>>> {
+	let mut factor = vec![vec![0.0; matrix.len()]; matrix.len()];
+	for i in 0 .. matrix.len() {
+		for j in 0 ..= i {
+			let sum: f64 = (0 .. j).map(|k| factor[i][k] * factor[j][k]).sum();
+			if i == j {
+				let diagonal = matrix[i][i] - sum;
+				if diagonal.is_nan() || diagonal <= 0.0 {
+					return None;
+				}
+				factor[i][i] = diagonal.sqrt();
+			} else {
+				factor[i][j] = (matrix[i][j] - sum) / factor[j][j];
+			}
+		}
+	}
+	Some(factor)
+}
+
+/// squared mahalanobis length x^T*S^-1*x for the cholesky factor L of S, solves L*y = x and
+/// returns |y|^2
+fn mahalanobis(factor: &[Vec<f64>], x: &[f64]) -> f64 {
+	let mut y = vec![0.0; x.len()];
+	for (i, x) in x.iter().enumerate() {
+		let sum: f64 = (0 .. i).map(|k| factor[i][k] * y[k]).sum();
+		y[i] = (x - sum) / factor[i][i];
+	}
+	y.iter().map(|y| y * y).sum()
+}
+
+/// reflect a value back and forth between min and max until it lies between them
+fn reflect(value: f64, min: f64, max: f64) -> f64 {
+	let width = max - min;
+	let offset = (value - min).rem_euclid(2.0 * width);
+	match offset > width {
+		true => max - (offset - width),
+		false => min + offset,
+	}
+}
+
+/// population variance of the values, 0 if there are none
+fn variance(values: &[f64]) -> f64 {
+	if values.is_empty() {
+		return 0.0;
+	}
+	let mean = values.iter().sum::<f64>() / values.len() as f64;
+	values.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / values.len() as f64
+}
+
+/// divide total into integer parts proportional to share, handing out the remainder to the parts
+/// with the largest fractional share
+fn apportion(share: &[f64], total: usize) -> Vec<usize> {
+	let sum: f64 = share.iter().sum();
+	let exact: Vec<f64> = share.iter().map(|x| x * total as f64 / sum).collect();
+	let mut parts: Vec<usize> = exact.iter().map(|x| x.floor() as usize).collect();
+	let remainder = total - parts.iter().sum::<usize>();
+
+	let mut order: Vec<usize> = (0 .. share.len()).collect();
+	order.sort_by(|&a, &b| (exact[b] - exact[b].floor()).total_cmp(&(exact[a] - exact[a].floor())));
+	for &index in order.iter().take(remainder) {
+		parts[index] += 1;
+	}
+	parts
 }
 
 #[inline]
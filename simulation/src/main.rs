@@ -1,40 +1,78 @@
-use simulation::{Environment::Constant, *};
+use simulation::*;
 use tinyvec::tiny_vec;
 
 fn main() {
-	let e = vec![0.5; 8].into_iter();
+	let e = vec![vec![0.5]; 8].into_iter();
 	let p = vec![
 		Patch::new(vec![
-			Individual {
-				loci: tiny_vec![0.1, 0.1, 0.1, 0.1],
-			};
+			Individual::new(tiny_vec![0.1, 0.1, 0.1, 0.1]);
 			10000 / 8
 		]);
 		8
 	];
-	let mut state = State {
-		tick:    0,
-		patches: p.clone().into_iter().zip(e).collect(),
-	};
-	let mut config = Config {
-		mutation_mu:     0.001,
-		mutation_sigma:  0.01,
-		mutation_step:   0.01,
-		rec:             0.01,
-		selection_sigma: 0.3,
-		gamma:           0.0,
-		diploid:         true,
-		m:               1.0,
-		environment:     Environment::Constant,
+	let mut state = State::new(p.clone().into_iter().zip(e).collect(), true, 0);
+	let config = Config {
+		mutation_mu:            0.001,
+		mutation_sigma:         0.01,
+		mutation_step:          0.01,
+		mutation_model:         MutationModel::DiscreteGaussian,
+		mutation_rates:         None,
+		mutator_sigma:          None,
+		markers:                None,
+		rec:                    0.01,
+		selfing:                0.0,
+		assortative_mating:     0.0,
+		selection_sigma:        0.3,
+		fitness:                FitnessFunction::Gaussian,
+		r_max:                  1.0,
+		competition_sigma:      None,
+		optima:                 vec![],
+		selection_covariance:   None,
+		dioecy:                 None,
+		plasticity:             None,
+		developmental_variance: 0.0,
+		gamma:                  0.0,
+		survival:               None,
+		seed_bank:              None,
+		m:                      1.0,
+		dispersal_sigma:        None,
+		hard_selection:         0.0,
+		population_dynamics:    None,
+		dispersal:              Dispersal::Global,
+		environment:            Environment::Constant,
 	};
 
-	let x = State::mutation(
+	let _ = state.mutation(
 		p,
 		config.mutation_mu,
 		config.mutation_sigma,
 		config.mutation_step,
+		&config.mutation_model,
+		config.mutation_rates.as_deref(),
 	);
-	for i in 0 .. 1000 {
-		step(&mut state, &config);
+	let mut detector = stats::MorphDetector::new(4);
+	for _ in 0 .. 1000 {
+		step(&mut state, &config).unwrap();
+		state.tick += 1;
+		if state.tick % 100 == 0 {
+			detector.detect(&state);
+		}
+	}
+
+	let phenotype = stats::phenotype(&state);
+	if let (Some(global), Some(fitness)) = (phenotype.global, stats::fitness(&state).global) {
+		println!(
+			"phenotype mean {:.4} variance {:.6} (between {:.6}, within {:.6}), mean fitness {:.4}",
+			global.mean, global.variance, phenotype.between, phenotype.within, fitness.mean
+		);
+	}
+	for morph in detector.detect(&state) {
+		println!(
+			"morph at {:.4} with frequency {:.3}",
+			morph.mean, morph.frequency
+		);
+	}
+	if let Some(tick) = detector.branching {
+		println!("branching was first detected at tick {}", tick);
 	}
 }
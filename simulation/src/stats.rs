@@ -0,0 +1,518 @@
+use std::collections::HashMap;
+
+use itertools::izip;
+use serde::{Deserialize, Serialize};
+
+use crate::{cholesky, variance, GenotypeMap, State};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Moments {
+	pub mean:     f64,
+	pub variance: f64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+	// mean and variance within every patch, None for an empty patch
+	pub patches: Vec<Option<Moments>>,
+	// mean and variance over the whole population, None if it is empty
+	pub global:  Option<Moments>,
+	// variance of the patch means weighted by the patch sizes
+	pub between: f64,
+	// variance within the patches weighted by the patch sizes, between + within is the global
+	// variance
+	pub within:  f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Heterozygosity {
+	// 1 - sum p^2 over the allele frequencies of the whole population, averaged over the loci
+	pub expected: f64,
+	// fraction of loci carrying two different alleles, None for haploids
+	pub observed: Option<f64>,
+}
+
+/// mean and variance of some values, None if there are none
+pub fn moments(values: &[f64]) -> Option<Moments> {
+	if values.is_empty() {
+		return None;
+	}
+	Some(Moments {
+		mean:     values.iter().sum::<f64>() / values.len() as f64,
+		variance: variance(values),
+	})
+}
+
+/// mean and variance of some values that are grouped per patch, with the global variance
+/// partitioned into the variance between and within the patches
+pub fn summary(groups: &[Vec<f64>]) -> Summary {
+	let patches: Vec<_> = groups.iter().map(|values| moments(values)).collect();
+	let all: Vec<f64> = groups.iter().flatten().copied().collect();
+	let global = moments(&all);
+
+	let (mut between, mut within) = (0.0, 0.0);
+	if let Some(global) = global {
+		for (values, patch) in groups.iter().zip(&patches) {
+			if let Some(patch) = patch {
+				let weight = values.len() as f64 / all.len() as f64;
+				between += weight * (patch.mean - global.mean).powi(2);
+				within += weight * patch.variance;
+			}
+		}
+	}
+	Summary {
+		patches,
+		global,
+		between,
+		within,
+	}
+}
+
+/// the expressed phenotype of the first trait in every patch
+pub fn phenotype(state: &State) -> Summary {
+	let groups: Vec<Vec<f64>> = state
+		.patches
+		.iter()
+		.enumerate()
+		.map(|(index, (patch, _))| {
+			patch
+				.iter()
+				.map(|individual| state.expressed(individual, index)[0])
+				.collect()
+		})
+		.collect();
+	summary(&groups)
+}
+
+/// the allelic values of every locus in every patch, both copies of a locus count for diploids
+pub fn loci(state: &State) -> Vec<Summary> {
+	let loci = state.loci();
+	let copies = if state.diploid { 2 } else { 1 };
+	(0 .. loci)
+		.map(|locus| {
+			let groups: Vec<Vec<f64>> = state
+				.patches
+				.iter()
+				.map(|(patch, _)| {
+					patch
+						.iter()
+						.flat_map(|individual| {
+							(0 .. copies).map(move |copy| individual[copy * loci + locus])
+						})
+						.collect()
+				})
+				.collect();
+			summary(&groups)
+		})
+		.collect()
+}
+
+/// breeding value of the first trait of every individual in every patch, the best linear
+/// prediction of its genotypic value from the allelic content of the loci of that trait, which is
+/// the allele sum under the additive map but not under dominance or epistasis
+pub fn breeding_values(state: &State) -> Vec<Vec<f64>> {
+	let loci = state.loci();
+	let copies = if state.diploid { 2 } else { 1 };
+	let first: Vec<usize> = (0 .. loci)
+		.filter(|locus| locus % state.traits.max(1) == 0)
+		.collect();
+	let content = |individual: &crate::Individual| -> Vec<f64> {
+		first
+			.iter()
+			.map(|&locus| {
+				(0 .. copies)
+					.map(|copy| individual[copy * loci + locus])
+					.sum()
+			})
+			.collect()
+	};
+	let individuals: Vec<_> = state
+		.patches
+		.iter()
+		.flat_map(|(patch, _)| patch.iter())
+		.collect();
+	if individuals.is_empty() {
+		return vec![Vec::new(); state.patches.len()];
+	}
+
+	let n = individuals.len() as f64;
+	let contents: Vec<Vec<f64>> = individuals
+		.iter()
+		.map(|individual| content(individual))
+		.collect();
+	let genotypic: Vec<f64> = individuals
+		.iter()
+		.map(|individual| state.traits(individual)[0])
+		.collect();
+	let mean: Vec<f64> = (0 .. first.len())
+		.map(|l| contents.iter().map(|x| x[l]).sum::<f64>() / n)
+		.collect();
+	let mean_genotypic = genotypic.iter().sum::<f64>() / n;
+	let mut covariance = vec![vec![0.0; first.len()]; first.len()];
+	let mut cross = vec![0.0; first.len()];
+	for (x, g) in contents.iter().zip(&genotypic) {
+		for i in 0 .. first.len() {
+			cross[i] += (x[i] - mean[i]) * (g - mean_genotypic) / n;
+			for j in 0 .. first.len() {
+				covariance[i][j] += (x[i] - mean[i]) * (x[j] - mean[j]) / n;
+			}
+		}
+	}
+	// a small ridge keeps the regression solvable with monomorphic or perfectly linked loci
+	let factor = cholesky(&covariance).or_else(|| {
+		let trace: f64 = (0 .. first.len()).map(|i| covariance[i][i]).sum();
+		let ridge = (1e-9 * trace).max(f64::MIN_POSITIVE);
+		for (i, row) in covariance.iter_mut().enumerate() {
+			row[i] += ridge;
+		}
+		cholesky(&covariance)
+	});
+	let effects = match factor {
+		Some(factor) => solve(&factor, &cross),
+		None => vec![0.0; first.len()],
+	};
+
+	state
+		.patches
+		.iter()
+		.map(|(patch, _)| {
+			patch
+				.iter()
+				.map(|individual| {
+					izip!(content(individual), &mean, &effects)
+						.map(|(x, mean, effect)| effect * (x - mean))
+						.sum()
+				})
+				.collect()
+		})
+		.collect()
+}
+
+/// solve L*L^T*x = b for the lower triangular cholesky factor L
+fn solve(factor: &[Vec<f64>], b: &[f64]) -> Vec<f64> {
+	let len = b.len();
+	let mut y = vec![0.0; len];
+	for i in 0 .. len {
+		let sum: f64 = (0 .. i).map(|k| factor[i][k] * y[k]).sum();
+		y[i] = (b[i] - sum) / factor[i][i];
+	}
+	let mut x = vec![0.0; len];
+	for i in (0 .. len).rev() {
+		let sum: f64 = (i + 1 .. len).map(|k| factor[k][i] * x[k]).sum();
+		x[i] = (y[i] - sum) / factor[i][i];
+	}
+	x
+}
+
+/// the reproductive success of every adult in every patch in the last tick, with the
+/// developmental noise it was selected with
+pub fn fitness(state: &State) -> Summary { summary(&state.success) }
+
+/// heterozygosity with the allelic values binned on a grid of the mutation step, None if the
+/// population is empty or the step is not strictly positive
+pub fn heterozygosity(state: &State, mutation_step: f64) -> Option<Heterozygosity> {
+	let loci = state.loci();
+	if loci == 0 || mutation_step.is_nan() || mutation_step <= 0.0 {
+		return None;
+	}
+	let individuals: Vec<_> = state
+		.patches
+		.iter()
+		.flat_map(|(patch, _)| patch.iter())
+		.collect();
+	let copies = if state.diploid { 2 } else { 1 };
+	let allele = |value: f64| (value / mutation_step).round() as i64;
+
+	let (mut expected, mut heterozygotes) = (0.0, 0);
+	for locus in 0 .. loci {
+		let mut counts = HashMap::new();
+		for individual in &individuals {
+			let alleles: Vec<i64> = (0 .. copies)
+				.map(|copy| allele(individual[copy * loci + locus]))
+				.collect();
+			if alleles.iter().any(|&a| a != alleles[0]) {
+				heterozygotes += 1;
+			}
+			for a in alleles {
+				*counts.entry(a).or_insert(0.0) += 1.0;
+			}
+		}
+		let total = (individuals.len() * copies) as f64;
+		expected += 1.0 - counts.values().map(|c| (c / total).powi(2)).sum::<f64>();
+	}
+	Some(Heterozygosity {
+		expected: expected / loci as f64,
+		observed: state
+			.diploid
+			.then(|| heterozygotes as f64 / (individuals.len() * loci) as f64),
+	})
+}
+
+/// linkage disequilibrium as the correlation r between the allelic values of every pair of loci
+/// over all haplotypes in the population, 0 where a locus does not vary
+pub fn linkage_disequilibrium(state: &State) -> Vec<Vec<f64>> {
+	let loci = state.loci();
+	if loci == 0 {
+		return Vec::new();
+	}
+	let copies = if state.diploid { 2 } else { 1 };
+	let haplotypes: Vec<&[f64]> = state
+		.patches
+		.iter()
+		.flat_map(|(patch, _)| patch.iter())
+		.flat_map(|individual| individual.chunks(loci).take(copies))
+		.collect();
+
+	let n = haplotypes.len() as f64;
+	let means: Vec<f64> = (0 .. loci)
+		.map(|l| haplotypes.iter().map(|h| h[l]).sum::<f64>() / n)
+		.collect();
+	let covariance = |i: usize, j: usize| {
+		haplotypes
+			.iter()
+			.map(|h| (h[i] - means[i]) * (h[j] - means[j]))
+			.sum::<f64>()
+			/ n
+	};
+	let variances: Vec<f64> = (0 .. loci).map(|l| covariance(l, l)).collect();
+	(0 .. loci)
+		.map(|i| {
+			(0 .. loci)
+				.map(|j| match variances[i] > 0.0 && variances[j] > 0.0 {
+					true => covariance(i, j) / (variances[i] * variances[j]).sqrt(),
+					false => 0.0,
+				})
+				.collect()
+		})
+		.collect()
+}
+
+/// F_ST on the neutral markers as (H_T - H_S)/H_T summed over the loci, with H_S the mean expected
+/// heterozygosity within the patches and H_T the expected heterozygosity of the mean allele
+/// frequencies, None without markers or without marker variation
+pub fn fst(state: &State) -> Option<f64> {
+	let patches: Vec<_> = state
+		.patches
+		.iter()
+		.map(|(patch, _)| patch)
+		.filter(|patch| !patch.is_empty())
+		.collect();
+	let ploidy = if state.diploid { 2 } else { 1 };
+	let loci = patches.first()?.first()?.markers.len() / ploidy;
+
+	let (mut total, mut within) = (0.0, 0.0);
+	for locus in 0 .. loci {
+		let frequencies: Vec<HashMap<i64, f64>> = patches
+			.iter()
+			.map(|patch| {
+				let mut counts = HashMap::new();
+				for individual in patch.iter() {
+					for copy in 0 .. ploidy {
+						*counts
+							.entry(individual.markers[copy * loci + locus])
+							.or_insert(0.0) += 1.0;
+					}
+				}
+				let alleles = (patch.len() * ploidy) as f64;
+				counts.values_mut().for_each(|count| *count /= alleles);
+				counts
+			})
+			.collect();
+
+		let mut mean = HashMap::new();
+		for patch in &frequencies {
+			within += 1.0 - patch.values().map(|p| p * p).sum::<f64>();
+			for (&allele, p) in patch {
+				*mean.entry(allele).or_insert(0.0) += p / patches.len() as f64;
+			}
+		}
+		total += 1.0 - mean.values().map(|p| p * p).sum::<f64>();
+	}
+	within /= patches.len() as f64;
+	(total > 0.0).then(|| (total - within) / total)
+}
+
+/// Q_ST on the additive genetic value of the first trait as V_B/(V_B + 2*V_W) for diploids or
+/// V_B/(V_B + V_W) for haploids, with V_B the variance of the patch means and V_W the mean
+/// variance within the patches, None without genetic variance
+pub fn qst(state: &State) -> Option<f64> {
+	let (mut means, mut variances) = (Vec::new(), Vec::new());
+	for (patch, _) in state.patches.iter().filter(|(patch, _)| !patch.is_empty()) {
+		let values: Vec<f64> = patch
+			.iter()
+			.map(|individual| {
+				GenotypeMap::Additive.traits(individual, state.diploid, state.traits)[0]
+			})
+			.collect();
+		means.push(values.iter().sum::<f64>() / values.len() as f64);
+		variances.push(variance(&values));
+	}
+	if means.is_empty() {
+		return None;
+	}
+
+	let between = variance(&means);
+	let within = variances.iter().sum::<f64>() / variances.len() as f64;
+	let ploidy = if state.diploid { 2.0 } else { 1.0 };
+	let total = between + ploidy * within;
+	(total > 0.0).then(|| between / total)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Morph {
+	pub mean:      f64,
+	pub variance:  f64,
+	// fraction of the population that belongs to the morph
+	pub frequency: f64,
+}
+
+/// fit mixtures of 1 up to components gaussians to the values with expectation maximisation and
+/// keep the one with the lowest BIC of which all neighbouring components are clearly separated
+/// (Ashman's D > 3), the morphs are sorted by their mean, more than a thousand values are thinned
+/// out evenly over their quantiles first
+pub fn mixture(values: &[f64], components: usize) -> Vec<Morph> {
+	let mut sorted: Vec<f64> = values.iter().copied().filter(|x| x.is_finite()).collect();
+	sorted.sort_by(f64::total_cmp);
+	// the spacing of the values if they lie on a grid, like the alleles of discrete mutations
+	let gap = sorted
+		.windows(2)
+		.map(|pair| pair[1] - pair[0])
+		.filter(|&gap| gap > 0.0)
+		.fold(f64::INFINITY, f64::min);
+	if sorted.len() > 1000 {
+		let len = sorted.len();
+		sorted = (0 .. 1000).map(|i| sorted[i * len / 1000]).collect();
+	}
+	let global = match moments(&sorted) {
+		Some(global) => global,
+		None => return Vec::new(),
+	};
+	if global.variance <= 0.0 {
+		return vec![Morph {
+			mean:      global.mean,
+			variance:  0.0,
+			frequency: 1.0,
+		}];
+	}
+
+	let n = sorted.len() as f64;
+	let floor = (global.variance * 1e-3).max(gap * gap).min(global.variance);
+	let mut best = (f64::INFINITY, Vec::new());
+	for k in 1 ..= components.max(1).min(sorted.len()) {
+		let (likelihood, morphs) = expectation_maximisation(&sorted, k, global.variance, floor);
+		let separated = morphs.windows(2).all(|pair| {
+			let d = 2f64.sqrt() * (pair[1].mean - pair[0].mean)
+				/ (pair[0].variance + pair[1].variance).sqrt();
+			d > 3.0
+		});
+		let bic = -2.0 * likelihood + (3 * k - 1) as f64 * n.ln();
+		if separated && bic < best.0 {
+			best = (bic, morphs);
+		}
+	}
+	best.1
+}
+
+/// log likelihood and components of a k component gaussian mixture fitted to sorted values, the
+/// components start at evenly spaced quantiles with the total variance and their variance cannot
+/// drop below the floor so no component collapses onto a single value
+fn expectation_maximisation(sorted: &[f64], k: usize, total: f64, floor: f64) -> (f64, Vec<Morph>) {
+	let n = sorted.len();
+	let mut morphs: Vec<Morph> = (0 .. k)
+		.map(|c| Morph {
+			mean:      sorted[(2 * c + 1) * n / (2 * k)],
+			variance:  total,
+			frequency: 1.0 / k as f64,
+		})
+		.collect();
+
+	let mut responsibilities = vec![vec![0.0; k]; n];
+	let mut previous = f64::NEG_INFINITY;
+	let mut likelihood = previous;
+	for _ in 0 .. 200 {
+		// expectation, with the log-sum-exp trick against underflow
+		likelihood = 0.0;
+		for (x, weights) in sorted.iter().zip(&mut responsibilities) {
+			for (weight, morph) in weights.iter_mut().zip(&morphs) {
+				*weight = morph.frequency.ln()
+					- 0.5 * (2.0 * std::f64::consts::PI * morph.variance).ln()
+					- (x - morph.mean).powi(2) / (2.0 * morph.variance);
+			}
+			let max = weights.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+			let sum = max + weights.iter().map(|w| (w - max).exp()).sum::<f64>().ln();
+			weights.iter_mut().for_each(|w| *w = (*w - sum).exp());
+			likelihood += sum;
+		}
+
+		// maximisation
+		for (c, morph) in morphs.iter_mut().enumerate() {
+			let weight: f64 = responsibilities.iter().map(|w| w[c]).sum();
+			if weight <= 0.0 {
+				morph.frequency = 0.0;
+				continue;
+			}
+			let mean = sorted
+				.iter()
+				.zip(&responsibilities)
+				.map(|(x, w)| w[c] * x)
+				.sum::<f64>()
+				/ weight;
+			let variance = sorted
+				.iter()
+				.zip(&responsibilities)
+				.map(|(x, w)| w[c] * (x - mean).powi(2))
+				.sum::<f64>()
+				/ weight;
+			*morph = Morph {
+				mean,
+				variance: variance.max(floor),
+				frequency: weight / n as f64,
+			};
+		}
+
+		if (likelihood - previous).abs() <= 1e-8 * likelihood.abs() {
+			break;
+		}
+		previous = likelihood;
+	}
+	morphs.retain(|morph| morph.frequency > 0.0);
+	morphs.sort_by(|a, b| a.mean.total_cmp(&b.mean));
+	(likelihood, morphs)
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MorphDetector {
+	// largest amount of morphs that is looked for
+	pub components: usize,
+	// tick at which more than one morph was detected for the first time
+	pub branching:  Option<u64>,
+}
+
+impl MorphDetector {
+	pub fn new(components: usize) -> MorphDetector {
+		Self {
+			components,
+			branching: None,
+		}
+	}
+
+	/// the morphs among the expressed phenotypes of the first trait, the tick of the state is
+	/// remembered the first time there is more than one
+	pub fn detect(&mut self, state: &State) -> Vec<Morph> {
+		let phenotypes: Vec<f64> = state
+			.patches
+			.iter()
+			.enumerate()
+			.flat_map(|(index, (patch, _))| {
+				patch
+					.iter()
+					.map(move |individual| state.expressed(individual, index)[0])
+			})
+			.collect();
+		let morphs = mixture(&phenotypes, self.components);
+		if morphs.len() > 1 && self.branching.is_none() {
+			self.branching = Some(state.tick);
+		}
+		morphs
+	}
+}
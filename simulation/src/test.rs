@@ -4,45 +4,365 @@ mod tests {
 
 	use crate::*;
 
+	/// a plain configuration without any of the optional mechanisms
+	fn config() -> Config {
+		Config {
+			mutation_mu:            0.001,
+			mutation_sigma:         0.01,
+			mutation_step:          0.01,
+			mutation_model:         MutationModel::DiscreteGaussian,
+			mutation_rates:         None,
+			mutator_sigma:          None,
+			markers:                None,
+			rec:                    0.01,
+			selfing:                0.0,
+			assortative_mating:     0.0,
+			selection_sigma:        0.3,
+			fitness:                FitnessFunction::Gaussian,
+			r_max:                  1.0,
+			competition_sigma:      None,
+			optima:                 vec![],
+			selection_covariance:   None,
+			dioecy:                 None,
+			plasticity:             None,
+			developmental_variance: 0.0,
+			gamma:                  0.0,
+			survival:               None,
+			seed_bank:              None,
+			m:                      1.0,
+			dispersal_sigma:        None,
+			hard_selection:         0.0,
+			population_dynamics:    None,
+			dispersal:              Dispersal::Global,
+			environment:            Environment::Constant,
+		}
+	}
+
 	#[test]
 	fn step_test() {
-		let e = vec![0.5; 8].into_iter();
+		let e = vec![vec![0.5]; 8].into_iter();
 		let p = vec![
 			Patch::new(vec![
-				Individual {
-					loci: tiny_vec![0.1, 0.1, 0.1, 0.1],
-				};
+				Individual::new(tiny_vec![0.1, 0.1, 0.1, 0.1]);
 				4000 / 8
 			]);
 			8
 		];
-		let mut state = State {
-			tick:    0,
-			patches: p.clone().into_iter().zip(e).collect(),
-		};
-		let mut config = Config {
-			mutation_mu:     0.001,
-			mutation_sigma:  0.01,
-			mutation_step:   0.01,
-			rec:             0.01,
-			r_max:           1000.0,
-			selection_sigma: 0.3,
-			gamma:           0.0,
-			diploid:         false,
-			m:               1.0,
-		};
-
-		let x = State::mutation(
+		let mut state = State::new(p.clone().into_iter().zip(e).collect(), false, 0);
+		let config = config();
+
+		let x = state.mutation(
 			p,
 			config.mutation_mu,
 			config.mutation_sigma,
 			config.mutation_step,
+			&config.mutation_model,
+			config.mutation_rates.as_deref(),
 		);
-		for i in 0 .. 100 {
-			step(&mut state, &config);
+		dbg!(x.len());
+		for _ in 0 .. 100 {
+			step(&mut state, &config).unwrap();
 		}
 	}
 
+	#[test]
+	fn reproducible() {
+		let init_config = InitConfig {
+			t_max:             None,
+			kind:              InitialPopulation::UniformI,
+			patches:           4,
+			individuals:       400,
+			loci:              2,
+			traits:            1,
+			diploid:           true,
+			genotype_map:      GenotypeMap::Additive,
+			recombination_map: None,
+			capacities:        Capacities::Even,
+			seed:              Some(42),
+		};
+		let config = Config {
+			mutation_mu:            0.1,
+			mutation_sigma:         0.01,
+			mutation_step:          0.01,
+			mutation_model:         MutationModel::DiscreteGaussian,
+			mutation_rates:         None,
+			mutator_sigma:          None,
+			markers:                None,
+			rec:                    0.1,
+			selfing:                0.0,
+			assortative_mating:     0.0,
+			selection_sigma:        0.3,
+			fitness:                FitnessFunction::Gaussian,
+			r_max:                  1.0,
+			competition_sigma:      None,
+			optima:                 vec![],
+			selection_covariance:   None,
+			dioecy:                 None,
+			plasticity:             None,
+			developmental_variance: 0.0,
+			gamma:                  0.5,
+			survival:               None,
+			seed_bank:              None,
+			m:                      0.5,
+			dispersal_sigma:        None,
+			hard_selection:         0.0,
+			population_dynamics:    None,
+			dispersal:              Dispersal::Global,
+			environment:            Environment::RandomWalk,
+		};
+
+		let run = || {
+			let mut state = init(init_config.clone(), config.environment.clone()).unwrap();
+			for _ in 0 .. 50 {
+				step(&mut state, &config).unwrap();
+				state.tick += 1;
+			}
+			state
+				.patches
+				.iter()
+				.flat_map(|(patch, env)| {
+					patch
+						.iter()
+						.map(|individual| state.phenotype(individual))
+						.chain(env.iter().copied())
+				})
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(run(), run());
+
+		// a fixed seed always makes the same run, also on other builds
+		let x = run();
+		let mean = x.iter().sum::<f64>() / x.len() as f64;
+		assert_eq!(x.len(), 404);
+		assert!((mean - 0.016522196769252696).abs() < 1e-12);
+		assert!((x[0] + 0.2111379539955709).abs() < 1e-12);
+		assert!((x[403] - 0.0053896131403826505).abs() < 1e-12);
+	}
+
+	#[test]
+	fn capacities() {
+		let geometric = Capacities::Geometric { ratio: 2.0 };
+		assert_eq!(geometric.resolve(3, 70), Ok(vec![10, 20, 40]));
+		assert!(Capacities::Even.resolve(4, 3).is_err());
+		assert!(Capacities::List(vec![1, 2]).resolve(3, 0).is_err());
+
+		let init_config = InitConfig {
+			t_max:             None,
+			kind:              InitialPopulation::Uniform,
+			patches:           3,
+			individuals:       0,
+			loci:              1,
+			traits:            1,
+			diploid:           false,
+			genotype_map:      GenotypeMap::Additive,
+			recombination_map: None,
+			capacities:        Capacities::List(vec![5, 1, 20]),
+			seed:              Some(0),
+		};
+		let mut state = init(init_config.clone(), Environment::Constant).unwrap();
+		let mut config = config();
+		for _ in 0 .. 10 {
+			step(&mut state, &config).unwrap();
+		}
+		let sizes: Vec<_> = state.patches.iter().map(|(patch, _)| patch.len()).collect();
+		assert_eq!(sizes, vec![5, 1, 20]);
+
+		// under hard selection the patches change in size, but never beyond their capacity
+		let mut state = init(init_config, Environment::Constant).unwrap();
+		config.hard_selection = 1.0;
+		for _ in 0 .. 10 {
+			step(&mut state, &config).unwrap();
+		}
+		assert!(izip!(&state.patches, &state.capacities).all(|((patch, _), &k)| patch.len() <= k));
+	}
+
+	#[test]
+	fn hard_selection() {
+		let init_config = InitConfig {
+			t_max:             None,
+			kind:              InitialPopulation::Constant,
+			patches:           4,
+			individuals:       400,
+			loci:              1,
+			traits:            1,
+			diploid:           false,
+			genotype_map:      GenotypeMap::Additive,
+			recombination_map: None,
+			capacities:        Capacities::Even,
+			seed:              Some(0),
+		};
+		let mut state = init(init_config.clone(), Environment::Constant).unwrap();
+		// every patch is told apart by its allele, and the patches differ in fitness
+		for (index, (patch, env)) in state.patches.iter_mut().enumerate() {
+			for individual in &mut **patch {
+				individual[0] = index as f64;
+			}
+			env[0] = 0.0;
+		}
+		let mut config = config();
+		config.m = 0.0;
+		config.mutation_mu = 0.0;
+		config.hard_selection = 1.0;
+		step(&mut state, &config).unwrap();
+
+		// without dispersal nobody changes patch, whatever the productivity of the patches
+		for (index, (patch, _)) in state.patches.iter().enumerate() {
+			assert!(patch.iter().all(|individual| individual[0] == index as f64));
+		}
+		// the fittest patch stays full while the others are left short
+		assert_eq!(state.patches[0].0.len(), 100);
+		assert!(state.patches[3].0.len() < 100);
+
+		// a little hard selection barely changes the patch sizes under dispersal
+		let mut state = init(init_config, Environment::Constant).unwrap();
+		config.m = 0.5;
+		config.hard_selection = 0.001;
+		for _ in 0 .. 10 {
+			step(&mut state, &config).unwrap();
+		}
+		assert!(state.patches.iter().all(|(patch, _)| patch.len() == 100));
+
+		config.hard_selection = 2.0;
+		assert!(config.validate(&state).is_err());
+		config.hard_selection = -0.5;
+		assert!(config.validate(&state).is_err());
+		config.hard_selection = f64::NAN;
+		assert!(config.validate(&state).is_err());
+	}
+
+	#[test]
+	fn population_dynamics() {
+		let init_config = InitConfig {
+			t_max:             None,
+			kind:              InitialPopulation::Constant,
+			patches:           4,
+			individuals:       200,
+			loci:              1,
+			traits:            1,
+			diploid:           false,
+			genotype_map:      GenotypeMap::Additive,
+			recombination_map: None,
+			capacities:        Capacities::Even,
+			seed:              Some(0),
+		};
+		let mut state = init(init_config.clone(), Environment::Constant).unwrap();
+		let mut config = config();
+		config.r_max = 2.0;
+		config.m = 0.1;
+		config.population_dynamics = Some(PopulationDynamics::Ricker);
+		assert!(config.validate(&state).is_ok());
+
+		// an empty patch is recolonised by the dispersers of its neighbours
+		for (patch, env) in &mut state.patches {
+			env[0] = patch
+				.first()
+				.map_or(0.0, |individual| individual.phenotype());
+		}
+		state.patches[0].0.individuals.clear();
+		for _ in 0 .. 20 {
+			step(&mut state, &config).unwrap();
+		}
+		assert!(!state.patches[0].0.is_empty());
+		assert!(izip!(&state.patches, &state.capacities).all(|((patch, _), &k)| patch.len() <= k));
+
+		// far from the optimum every patch shrinks until the population is extinct
+		let mut state = init(init_config, Environment::Constant).unwrap();
+		for (_, env) in &mut state.patches {
+			env[0] = 100.0;
+		}
+		config.population_dynamics = Some(PopulationDynamics::BevertonHolt);
+		for _ in 0 .. 5 {
+			step(&mut state, &config).unwrap();
+		}
+		assert!(state.patches.iter().all(|(patch, _)| patch.is_empty()));
+
+		config.r_max = 1.0;
+		assert!(config.validate(&state).is_err());
+	}
+
+	#[test]
+	fn correlated_environment() {
+		let mut rng = ChaCha8Rng::seed_from_u64(0);
+		let patches = 2000;
+
+		// the patches all share the same innovation
+		let env = Patch::correlated_env(vec![0.0; patches], 1.0, 0.0, 1.0, &mut rng);
+		assert!(env.iter().all(|x| (x - env[0]).abs() < 1e-12));
+
+		// the patches are independent with the given variance
+		let env = Patch::correlated_env(vec![0.0; patches], 0.25, 0.0, 0.0, &mut rng);
+		let variance = env.iter().map(|x| x * x).sum::<f64>() / patches as f64;
+		assert!((variance - 0.25).abs() < 0.05);
+
+		// full autocorrelation keeps the environment constant
+		let next = Patch::correlated_env(env.clone(), 0.25, 1.0, 0.0, &mut rng);
+		assert_eq!(env, next);
+	}
+
+	#[test]
+	fn sine_environment() {
+		// the defaults reproduce the old hard-coded sinusoid
+		let (len, tick, factor) = (7, 1234, 20_000.0);
+		let old: Vec<_> = (0 .. len)
+			.map(|x| 0.5 * (2.0 * (x as f64 * PI / len as f64 + tick as f64 * PI / factor)).sin())
+			.collect();
+		let new = Patch::sine_env(len, tick, factor, 0.5, 1.0, 0.0);
+		for (old, new) in old.into_iter().zip(new) {
+			assert!((old - new).abs() < 1e-12);
+		}
+
+		// without phase spread all patches share the same optimum around the mean
+		let env = Patch::sine_env(4, 250, 1000.0, 2.0, 0.0, 1.0);
+		assert!(env.iter().all(|x| (x - 3.0).abs() < 1e-12));
+	}
+
+	#[test]
+	fn series_environment() {
+		let text = "patch 1,patch 2\n0.1,0.2\n0.3,0.4\n\n0.5,0.6\n";
+		let series = parse_series(text, 2, 1).unwrap();
+		assert_eq!(series.len(), 3);
+		assert!(parse_series(text, 3, 1).is_err());
+
+		assert_eq!(
+			Patch::series_env(&series, 2, 0, 1, 1, false),
+			vec![0.3, 0.4]
+		);
+		assert_eq!(
+			Patch::series_env(&series, 2, 0, 1, 7, false),
+			vec![0.5, 0.6]
+		);
+		assert_eq!(Patch::series_env(&series, 2, 0, 1, 4, true), vec![0.3, 0.4]);
+		assert_eq!(
+			Patch::series_env(&[vec![1.0]], 3, 0, 1, 0, true),
+			vec![1.0; 3]
+		);
+		// a single patch with a column for each of two traits
+		assert!(parse_series(text, 1, 2).is_ok());
+		assert_eq!(Patch::series_env(&series, 1, 1, 2, 0, false), vec![0.2]);
+
+		// only files inside the series directory can be read
+		assert_eq!(
+			series_path("climate/europe.csv"),
+			Ok(Path::new(SERIES_DIR).join("climate/europe.csv"))
+		);
+		for name in [
+			"",
+			"/etc/passwd",
+			"../secret.csv",
+			"climate/../../secret.csv",
+		] {
+			assert!(series_path(name).is_err());
+		}
+
+		// a series that cannot be read stops the simulation instead of keeping the environment
+		let mut state = State::new(vec![(Patch::new(Vec::new()), vec![0.0])], false, 0);
+		let missing = Environment::FromSeries {
+			path:   "missing.csv".to_string(),
+			repeat: false,
+		};
+		assert!(state.environment(&missing, 0).is_err());
+	}
+
 	#[test]
 	fn distr() {
 		let distr = WeightedAliasIndex::new(vec![0.0, 200.0, 400.0]).unwrap();
@@ -52,98 +372,681 @@ mod tests {
 
 	#[test]
 	fn reproduction() {
-		let state = State {
-			tick:    0,
-			patches: vec![(
+		let state = State::new(
+			vec![(
 				Patch {
-					individuals: vec![
-						Individual {
-							loci: tiny_vec!(0.0),
-						};
-						50
-					],
+					individuals: vec![Individual::new(tiny_vec!(0.0)); 50],
 				},
-				1.0,
+				vec![1.0],
 			)],
-		};
-		let x = state.reproduction(10.0, 1.0);
+			false,
+			0,
+		);
+		let x = state.reproduction(
+			10.0,
+			&FitnessFunction::Gaussian,
+			1.0,
+			None,
+			&[],
+			None,
+			None,
+			None,
+		);
 		dbg!(x);
 	}
 
+	#[test]
+	fn fitness_functions() {
+		let gaussian = FitnessFunction::Gaussian;
+		assert_eq!(gaussian.fitness(0.0, 0.3), 1.0);
+		assert!((gaussian.fitness(0.3, 0.3) - (-0.5f64).exp()).abs() < 1e-12);
+
+		let quadratic = FitnessFunction::Quadratic;
+		assert!((quadratic.fitness(-0.3, 0.3) - 0.5).abs() < 1e-12);
+		assert_eq!(quadratic.fitness(1.0, 0.3), 0.0);
+
+		let truncation = FitnessFunction::Truncation;
+		assert_eq!(truncation.fitness(0.2, 0.3), 1.0);
+		assert_eq!(truncation.fitness(-0.4, 0.3), 0.0);
+
+		let plateau = FitnessFunction::Plateau { width: 0.5 };
+		assert_eq!(plateau.fitness(-0.5, 0.3), 1.0);
+		assert!((plateau.fitness(0.8, 0.3) - gaussian.fitness(0.3, 0.3)).abs() < 1e-12);
+	}
+
+	#[test]
+	fn genotype_map() {
+		let individual = Individual::new(tiny_vec!(0.1, 0.3, 0.5, 0.1));
+		let additive = GenotypeMap::Additive.phenotype(&individual, true);
+		assert!((additive - 1.0).abs() < 1e-12);
+
+		let codominant = GenotypeMap::Dominance { h: 0.5 }.phenotype(&individual, true);
+		assert!((codominant - additive).abs() < 1e-12);
+		// the larger allele at both loci is fully dominant
+		let dominant = GenotypeMap::Dominance { h: 1.0 }.phenotype(&individual, true);
+		assert!((dominant - 1.6).abs() < 1e-12);
+		let recessive = GenotypeMap::Dominance { h: 0.0 }.phenotype(&individual, true);
+		assert!((recessive - 0.4).abs() < 1e-12);
+
+		// locus effects 0.6 and 0.4
+		let epistasis = GenotypeMap::Epistasis { epsilon: 1.0 }.phenotype(&individual, true);
+		assert!((epistasis - 1.24).abs() < 1e-12);
+		let haploid = GenotypeMap::Epistasis { epsilon: 1.0 }.phenotype(&individual, false);
+		assert!((haploid - (1.0 + 0.03 + 0.05 + 0.01 + 0.15 + 0.03 + 0.05)).abs() < 1e-12);
+	}
+
+	#[test]
+	fn multivariate_selection() {
+		let individual = Individual::new(tiny_vec!(0.1, 0.2, 0.3, 0.4));
+		let mut state = State::new(
+			vec![(Patch::new(vec![individual.clone()]), vec![0.5, 0.5])],
+			false,
+			0,
+		);
+		state.traits = 2;
+		let traits = state.traits(&individual);
+		assert!((traits[0] - 0.4).abs() < 1e-12 && (traits[1] - 0.6).abs() < 1e-12);
+
+		// the optima move in opposite directions with the environment, deviations 0.1 and -1.1
+		let optima = [(0.0, 1.0), (0.0, -1.0)];
+		let gaussian = FitnessFunction::Gaussian;
+		let isotropic = state.reproduction(1.0, &gaussian, 1.0, None, &optima, None, None, None);
+		let identity = vec![vec![1.0, 0.0], vec![0.0, 1.0]];
+		let covariance = state.reproduction(
+			1.0,
+			&gaussian,
+			1.0,
+			None,
+			&optima,
+			Some(&identity),
+			None,
+			None,
+		);
+		assert!((isotropic[0][0] - covariance[0][0]).abs() < 1e-12);
+
+		let wide = vec![vec![1.0, 0.0], vec![0.0, 4.0]];
+		let success =
+			state.reproduction(1.0, &gaussian, 1.0, None, &optima, Some(&wide), None, None);
+		let expected = (-(0.01 + 1.21 / 4.0) / 2.0f64).exp();
+		assert!((success[0][0] - expected).abs() < 1e-12);
+
+		// every trait follows its own component of the environment
+		state.patches[0].1 = vec![0.4, 0.6];
+		let success = state.reproduction(1.0, &gaussian, 1.0, None, &[], None, None, None);
+		assert!((success[0][0] - 1.0).abs() < 1e-12);
+
+		let init_config = InitConfig {
+			t_max:             None,
+			kind:              InitialPopulation::Uniform,
+			patches:           3,
+			individuals:       30,
+			loci:              2,
+			traits:            2,
+			diploid:           false,
+			genotype_map:      GenotypeMap::Additive,
+			recombination_map: None,
+			capacities:        Capacities::Even,
+			seed:              Some(0),
+		};
+		let mut state = init(init_config, Environment::Random).unwrap();
+		state.environment(&Environment::Random, 1).unwrap();
+		assert!(state
+			.patches
+			.iter()
+			.all(|(_, env)| env.len() == 2 && env[0] != env[1]));
+	}
+
+	#[test]
+	fn competition() {
+		let mut individuals = vec![Individual::new(tiny_vec!(-0.5)); 9];
+		individuals.push(Individual::new(tiny_vec!(0.5)));
+		let state = State::new(vec![(Patch::new(individuals), vec![0.0])], false, 0);
+
+		// both morphs are equally far from the optimum, the rare one suffers less competition
+		let success = state.reproduction(
+			1.0,
+			&FitnessFunction::Gaussian,
+			1.0,
+			None,
+			&[],
+			None,
+			None,
+			None,
+		);
+		assert_eq!(success[0][0], success[0][9]);
+		let success = state.reproduction(
+			1.0,
+			&FitnessFunction::Gaussian,
+			1.0,
+			Some(0.1),
+			&[],
+			None,
+			None,
+			None,
+		);
+		assert!((success[0][9] / success[0][0] - 9.0).abs() < 1e-6);
+	}
+
+	#[test]
+	fn plasticity() {
+		let plastic = Individual {
+			slope: Some(1.0),
+			..Individual::new(tiny_vec!(0.0))
+		};
+		let individuals = vec![Individual::new(tiny_vec!(0.0)), plastic];
+		let mut state = State::new(vec![(Patch::new(individuals), vec![1.0])], false, 0);
+		let mut plasticity = Plasticity {
+			sigma:     0.1,
+			cue_noise: 0.0,
+			cue_lag:   1,
+			cost:      0.0,
+		};
+		state.cue(Some(&plasticity));
+		assert_eq!(state.cues, vec![1.0]);
+		// the cue lags a tick behind the environment
+		state.patches[0].1[0] = 2.0;
+		state.cue(Some(&plasticity));
+		assert_eq!(state.cues, vec![1.0]);
+		assert_eq!(state.expressed(&state.patches[0].0[1], 0), vec![1.0]);
+
+		state.patches[0].1[0] = 1.0;
+		let gaussian = FitnessFunction::Gaussian;
+		let success = state.reproduction(
+			1.0,
+			&gaussian,
+			1.0,
+			None,
+			&[],
+			None,
+			None,
+			Some(&plasticity),
+		);
+		assert!((success[0][1] - 1.0).abs() < 1e-12);
+		assert!(success[0][0] < success[0][1]);
+		plasticity.cost = 0.5;
+		let success = state.reproduction(
+			1.0,
+			&gaussian,
+			1.0,
+			None,
+			&[],
+			None,
+			None,
+			Some(&plasticity),
+		);
+		assert!((success[0][1] - (-0.5f64).exp()).abs() < 1e-12);
+
+		state.cue(None);
+		assert!(state.cues.is_empty());
+	}
+
+	#[test]
+	fn dioecy() {
+		let sexed = |locus: f64, sex: Sex| Individual {
+			sex: Some(sex),
+			..Individual::new(tiny_vec!(locus, 0.0))
+		};
+		let individuals = vec![sexed(-0.25, Sex::Female), sexed(0.25, Sex::Male)];
+		let mut state = State::new(vec![(Patch::new(individuals), vec![0.0])], true, 0);
+		let dioecy = Dioecy {
+			offset: 0.25,
+			sigma:  Some(0.1),
+		};
+		// both sexes sit on their own optimum
+		let gaussian = FitnessFunction::Gaussian;
+		let success = state.reproduction(1.0, &gaussian, 1.0, None, &[], None, Some(&dioecy), None);
+		assert_eq!(success, vec![vec![1.0, 1.0]]);
+		let success = state.reproduction(1.0, &gaussian, 1.0, None, &[], None, None, None);
+		assert!(success[0][0] < 1.0);
+
+		// males keep their own selection strength on a selection covariance matrix
+		let weak = Dioecy {
+			offset: 0.0,
+			sigma:  Some(2.0),
+		};
+		let covariance = vec![vec![1.0]];
+		let x = state.reproduction(
+			1.0,
+			&gaussian,
+			1.0,
+			None,
+			&[],
+			Some(&covariance),
+			Some(&weak),
+			None,
+		);
+		let expected = |sigma: f64| (-0.0625 / (2.0 * sigma * sigma)).exp();
+		assert!((x[0][0] - expected(1.0)).abs() < 1e-12);
+		assert!((x[0][1] - expected(2.0)).abs() < 1e-12);
+
+		// every mother is followed by a father
+		let parents = state.density_regulation(success, &[50], true, true);
+		assert!(parents[0]
+			.chunks(2)
+			.all(|pair| pair[0].sex == Some(Sex::Female) && pair[1].sex == Some(Sex::Male)));
+		let offspring = state.recombination(parents, 0.5);
+		let sons = offspring[0]
+			.iter()
+			.filter(|individual| individual.sex == Some(Sex::Male))
+			.count();
+		assert!(sons > 0 && sons < 50);
+
+		// a patch of a single sex cannot reproduce
+		state.patches[0].0.individuals.truncate(1);
+		let lonely = state.density_regulation(vec![vec![1.0]], &[10], true, true);
+		assert!(lonely[0].is_empty());
+
+		state.sexes(None);
+		assert!(state.patches[0]
+			.0
+			.iter()
+			.all(|individual| individual.sex.is_none()));
+		state.sexes(Some(&dioecy));
+		assert!(state.patches[0]
+			.0
+			.iter()
+			.all(|individual| individual.sex.is_some()));
+
+		let mut config = config();
+		config.dioecy = Some(dioecy);
+		state.diploid = false;
+		assert!(config.validate(&state).is_err());
+
+		let init_config = InitConfig {
+			t_max:             None,
+			kind:              InitialPopulation::Uniform,
+			patches:           2,
+			individuals:       200,
+			loci:              2,
+			traits:            1,
+			diploid:           true,
+			genotype_map:      GenotypeMap::Additive,
+			recombination_map: None,
+			capacities:        Capacities::Even,
+			seed:              Some(0),
+		};
+		let mut state = init(init_config, Environment::Constant).unwrap();
+		for _ in 0 .. 10 {
+			step(&mut state, &config).unwrap();
+		}
+		assert!(state
+			.patches
+			.iter()
+			.flat_map(|(patch, _)| patch.iter())
+			.any(|individual| individual.sex == Some(Sex::Female)));
+	}
+
+	#[test]
+	fn heritability() {
+		let individuals = (0 .. 100)
+			.map(|i| Individual::new(tiny_vec!(i as f64 / 100.0)))
+			.collect();
+		let mut state = State::new(vec![(Patch::new(individuals), vec![0.0])], false, 0);
+		state.development(0.0);
+		assert!((state.heritability.unwrap() - 1.0).abs() < 1e-12);
+		let gaussian = FitnessFunction::Gaussian;
+		let deterministic = state.reproduction(1.0, &gaussian, 1.0, None, &[], None, None, None);
+
+		state.development(1.0);
+		assert!(state.heritability.unwrap() < 0.5);
+		let noisy = state.reproduction(1.0, &gaussian, 1.0, None, &[], None, None, None);
+		assert_ne!(deterministic, noisy);
+		// the reported fitness is the reproductive success of the adults of the last tick
+		assert!(stats::fitness(&state).global.is_none());
+		let mut config = config();
+		config.selection_sigma = 1.0;
+		step(&mut state, &config).unwrap();
+		let fitness = stats::fitness(&state).global.unwrap().mean;
+		let mean = deterministic[0].iter().sum::<f64>() / 100.0;
+		assert!((fitness - mean).abs() < 1e-12);
+
+		// with the larger allele recessive the breeding values are not the allele sums, here the
+		// regression of the genotypic values 0, 0, 0 and 2 on the allele counts 0, 0, 1 and 2
+		let individuals = [(0.0, 0.0), (0.0, 0.0), (0.0, 1.0), (1.0, 1.0)]
+			.iter()
+			.map(|&(a, b)| Individual::new(tiny_vec!(a, b)))
+			.collect();
+		let mut state = State::new(vec![(Patch::new(individuals), vec![0.0])], true, 0);
+		state.genotype_map = GenotypeMap::Dominance { h: 0.0 };
+		state.development(0.0);
+		let expected = 0.625 * 0.625 / 0.6875 / 0.75;
+		assert!((state.heritability.unwrap() - expected).abs() < 1e-12);
+
+		let mut state = State::new(
+			vec![(Patch::new(vec![Default::default(); 10]), vec![0.0])],
+			false,
+			0,
+		);
+		state.development(0.0);
+		assert_eq!(state.heritability, None);
+	}
+
 	#[test]
 	fn adult_death() {
-		let mut state = State {
-			tick:    0,
-			patches: vec![(
+		let mut state = State::new(
+			vec![(
 				Patch {
-					individuals: vec![
-						Individual {
-							loci: tiny_vec!(0.0),
-						};
-						10
-					],
+					individuals: vec![Individual::new(tiny_vec!(0.0)); 10],
 				},
-				1.0,
+				vec![1.0],
 			)],
-		};
-		let x = state.adult_death(0.3);
+			false,
+			0,
+		);
+		let x = state.adult_death(0.3, None);
 		dbg!(x);
 	}
 
+	#[test]
+	fn age_structure() {
+		let old = Individual {
+			age: 1,
+			..Individual::new(tiny_vec!(1.0))
+		};
+		let individuals = vec![Individual::new(tiny_vec!(0.0)), old.clone(), old];
+		let mut state = State::new(vec![(Patch::new(individuals), vec![0.0])], false, 0);
+		// only the young survive
+		let death = state.adult_death(0.0, Some(&[1.0, 0.0]));
+		assert_eq!(death, vec![2]);
+		let offspring = vec![Patch::new(vec![
+			Individual {
+				parent_age: 2.0,
+				..Individual::new(tiny_vec!(2.0))
+			};
+			2
+		])];
+		state.update(offspring, death);
+		assert_eq!(state.generation_time, Some(3.0));
+		assert_eq!(state.age_distribution(), vec![2, 1]);
+		assert_eq!(state.patches[0].0[0][0], 0.0);
+
+		// a recombinant is a newborn of its own parents, not of the individual in its slot
+		let aged = |age| Individual {
+			age,
+			..Individual::new(tiny_vec!(0.0, 0.0))
+		};
+		let parents = vec![Patch::new(vec![aged(9), aged(9), aged(1), aged(3)])];
+		let mut state = State::new(vec![(parents[0].clone(), vec![0.0])], true, 0);
+		let parents: Vec<Patch> = parents
+			.into_iter()
+			.map(|patch| Patch::new(patch.iter().map(Individual::offspring).collect()))
+			.collect();
+		let x = state.recombination(parents, 0.5);
+		assert!(x[0].iter().all(|individual| individual.age == 0));
+		assert_eq!(x[0][1].parent_age, 2.0);
+		assert_eq!(x[0][0].parent_age, 9.0);
+	}
+
+	#[test]
+	fn seed_bank() {
+		let mut state = State::new(vec![], false, 0);
+		state.dormant = vec![Patch::new(vec![Individual::new(tiny_vec!(1.0)); 5])];
+		let seed_bank = SeedBank {
+			dormancy:    1.0,
+			germination: 1.0,
+			decay:       0.0,
+		};
+		let y = vec![Patch::new(vec![Individual::new(tiny_vec!(0.0)); 10])];
+		// the whole seed bank germinates while every offspring goes dormant
+		let x = state.dormancy(y, &seed_bank, &[10]);
+		assert_eq!(x[0].len(), 5);
+		assert!(x[0].iter().all(|individual| individual[0] == 1.0));
+		assert_eq!(state.dormant[0].len(), 10);
+
+		let seed_bank = SeedBank {
+			dormancy:    0.0,
+			germination: 0.0,
+			decay:       1.0,
+		};
+		let y = vec![Patch::new(vec![Individual::new(tiny_vec!(2.0)); 10])];
+		let x = state.dormancy(y, &seed_bank, &[10]);
+		assert!(x[0].iter().all(|individual| individual[0] == 2.0));
+		assert!(state.dormant[0].is_empty());
+
+		// about the germination probability of the seed bank germinates every tick
+		state.dormant = vec![Patch::new(vec![Individual::new(tiny_vec!(1.0)); 10000])];
+		let seed_bank = SeedBank {
+			dormancy:    0.0,
+			germination: 0.1,
+			decay:       0.0,
+		};
+		let x = state.dormancy(vec![Patch::new(Vec::new())], &seed_bank, &[10000]);
+		assert!((x[0].len() as f64 - 1000.0).abs() < 100.0);
+		assert_eq!(x[0].len() + state.dormant[0].len(), 10000);
+
+		// the seed bank builds up under soft selection
+		let init_config = InitConfig {
+			t_max:             None,
+			kind:              InitialPopulation::Constant,
+			patches:           4,
+			individuals:       400,
+			loci:              1,
+			traits:            1,
+			diploid:           false,
+			genotype_map:      GenotypeMap::Additive,
+			recombination_map: None,
+			capacities:        Capacities::Even,
+			seed:              Some(0),
+		};
+		let mut state = init(init_config, Environment::Constant).unwrap();
+		let mut soft = config();
+		soft.seed_bank = Some(SeedBank {
+			dormancy:    0.5,
+			germination: 0.1,
+			decay:       0.01,
+		});
+		let mut sizes = Vec::new();
+		for _ in 0 .. 200 {
+			step(&mut state, &soft).unwrap();
+			sizes.push(state.dormant.iter().map(|pool| pool.len()).sum::<usize>());
+		}
+		assert!(sizes[199] > sizes[9]);
+		assert!(state.dormant.iter().all(|pool| pool.len() > 100));
+
+		// an extinct patch without immigrants is refilled from its seed bank
+		let init_config = InitConfig {
+			t_max:             None,
+			kind:              InitialPopulation::Constant,
+			patches:           2,
+			individuals:       100,
+			loci:              1,
+			traits:            1,
+			diploid:           false,
+			genotype_map:      GenotypeMap::Additive,
+			recombination_map: None,
+			capacities:        Capacities::Even,
+			seed:              Some(0),
+		};
+		let mut state = init(init_config, Environment::Constant).unwrap();
+		state.patches[0].0.individuals.clear();
+		state.dormant = vec![
+			Patch::new(vec![Individual::new(tiny_vec!(0.0)); 20]),
+			Patch::new(Vec::new()),
+		];
+		let mut config = config();
+		config.m = 0.0;
+		config.r_max = 2.0;
+		config.population_dynamics = Some(PopulationDynamics::BevertonHolt);
+		config.seed_bank = Some(SeedBank {
+			dormancy:    0.0,
+			germination: 1.0,
+			decay:       0.0,
+		});
+		let mut half = state.clone();
+		step(&mut state, &config).unwrap();
+		assert_eq!(state.patches[0].0.len(), 20);
+		assert!(state.dormant[0].is_empty());
+
+		// only the seeds that germinate take a spot, the others stay in the seed bank
+		config.seed_bank = Some(SeedBank {
+			dormancy:    0.0,
+			germination: 0.5,
+			decay:       0.0,
+		});
+		step(&mut half, &config).unwrap();
+		assert!(half.patches[0].0.len() < 20);
+		assert_eq!(half.patches[0].0.len() + half.dormant[0].len(), 20);
+
+		// seeds of newborn parents that rest three ticks make a generation time of four ticks
+		let adults = vec![Individual::new(tiny_vec!(0.0)); 10];
+		let mut state = State::new(vec![(Patch::new(adults), vec![0.0])], false, 0);
+		state.dormant = vec![Patch::new(vec![Individual::new(tiny_vec!(1.0)); 10])];
+		let resting = SeedBank {
+			dormancy:    0.0,
+			germination: 0.0,
+			decay:       0.0,
+		};
+		for _ in 0 .. 2 {
+			state.dormancy(vec![Patch::new(Vec::new())], &resting, &[0]);
+		}
+		let germinating = SeedBank {
+			germination: 1.0,
+			..resting
+		};
+		let x = state.dormancy(vec![Patch::new(Vec::new())], &germinating, &[10]);
+		assert!(x[0].iter().all(|individual| individual.age == 3));
+		state.update(x, vec![10]);
+		assert_eq!(state.generation_time, Some(4.0));
+	}
+
 	#[test]
 	fn density_regulation() {
-		let state = State {
-			tick:    0,
-			patches: vec![(
+		let mut state = State::new(
+			vec![(
 				Patch {
 					individuals: vec![
-						Individual {
-							loci: tiny_vec!(0.0),
-						},
-						Individual {
-							loci: tiny_vec!(1.0),
-						},
+						Individual::new(tiny_vec!(0.0)),
+						Individual::new(tiny_vec!(1.0)),
 					],
 				},
-				1.0,
+				vec![1.0],
 			)],
-		};
-		let y = state.reproduction(10.0, 0.5);
+			false,
+			0,
+		);
+		let y = state.reproduction(
+			0.5,
+			&FitnessFunction::Gaussian,
+			1.0,
+			None,
+			&[],
+			None,
+			None,
+			None,
+		);
 		dbg!(&y);
 		let death = vec![10];
-		let x = state.density_regulation(y, &death);
+		let x = state.density_regulation(y, &death, true, false);
 		dbg!(x);
 	}
 
+	#[test]
+	fn offspring() {
+		let state = State::new(vec![], false, 0);
+		let success = vec![vec![1.0; 10], vec![0.5; 10], vec![0.0; 10]];
+		let death = vec![4, 4, 4];
+
+		assert_eq!(
+			state.offspring(&success, &death, 0.0),
+			(death.clone(), vec![0; 3])
+		);
+		let hard = state.offspring(&success, &death, 1.0);
+		assert_eq!(hard, (vec![0; 3], vec![8, 4, 0]));
+		let mixed = state.offspring(&success, &death, 0.5);
+		assert_eq!(mixed, (vec![2; 3], vec![4, 2, 0]));
+	}
+
 	#[test]
 	fn recombination() {
 		let y = vec![Patch {
 			individuals: vec![
-				Individual {
-					loci: tiny_vec!(0.0, 1.0, 2.0, 3.0),
-				},
-				Individual {
-					loci: tiny_vec!(4.0, 5.0, 6.0, 7.0),
-				},
+				Individual::new(tiny_vec!(0.0, 1.0, 2.0, 3.0)),
+				Individual::new(tiny_vec!(4.0, 5.0, 6.0, 7.0)),
 			],
 		}];
-		let x = State::recombination(y, 0.5);
+		let mut state = State::new(vec![(y[0].clone(), vec![0.0])], true, 0);
+		let x = state.recombination(y, 0.5);
 		dbg!(x);
 	}
 
+	#[test]
+	fn mating() {
+		let morph = |locus: f64| Individual::new(tiny_vec!(locus, 0.0));
+		let parents = Patch::new(
+			(0 .. 200)
+				.map(|i| morph(if i % 4 < 2 { -1.0 } else { 1.0 }))
+				.collect(),
+		);
+		let mut state = State::new(vec![(parents.clone(), vec![0.0])], true, 0);
+		let pairs = |patches: &[Patch]| {
+			patches[0]
+				.chunks(2)
+				.map(|pair| (pair[0][0], pair[1][0]))
+				.collect::<Vec<_>>()
+		};
+
+		// the parents were drawn as -1, -1, 1, 1, ... so all pairs start out between alike morphs
+		let shuffled = state.mating(vec![parents.clone()], 0.0, 0.0);
+		assert!(pairs(&shuffled).iter().all(|(a, b)| a == b));
+
+		let mut mixed = parents.clone();
+		mixed.individuals.swap(1, 2);
+		let selfed = state.mating(vec![mixed], 1.0, 0.0);
+		assert!(pairs(&selfed).iter().all(|(a, b)| a == b));
+
+		let mut mixed = parents;
+		mixed.individuals.rotate_left(1);
+		let assortative = state.mating(vec![mixed], 0.0, 100.0);
+		assert!(pairs(&assortative).iter().all(|(a, b)| a == b));
+
+		let mut config = config();
+		config.selfing = 0.5;
+		config.dioecy = Some(Dioecy {
+			offset: 0.0,
+			sigma:  None,
+		});
+		assert!(config.validate(&state).is_err());
+	}
+
+	#[test]
+	fn recombination_map() {
+		let parents = Patch::new(vec![
+			Individual::new(tiny_vec!(
+				0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0
+			));
+			200
+		]);
+		let mut state = State::new(vec![(parents.clone(), vec![0.0])], true, 0);
+		let mixed = |patches: Vec<Patch>| {
+			patches[0]
+				.iter()
+				.flat_map(|individual| individual.chunks(4).map(|gamete| gamete.to_vec()))
+				.filter(|gamete| gamete.iter().any(|x| *x != gamete[0]))
+				.count()
+		};
+
+		// without crossovers every gamete is one of the parental haplotypes
+		state.recombination_map = Some(vec![0.0, 0.0, 0.0]);
+		let offspring = state.recombination(vec![parents.clone()], 0.5);
+		assert_eq!(offspring[0].len(), 100);
+		assert_eq!(mixed(offspring), 0);
+
+		// unlinked loci
+		state.recombination_map = Some(vec![0.5, 0.5, 0.5]);
+		let offspring = state.recombination(vec![parents], 0.0);
+		assert!(mixed(offspring) > 0);
+	}
+
 	#[test]
 	fn hapoloid_generation() {
-		let y = vec![Patch {
+		let y = Patch {
 			individuals: vec![
-				Individual {
-					loci: tiny_vec!(0.0, 1.0, 2.0, 3.0),
-				},
-				Individual {
-					loci: tiny_vec!(4.0, 5.0, 6.0, 7.0),
-				},
+				Individual::new(tiny_vec!(0.0, 1.0, 2.0, 3.0)),
+				Individual::new(tiny_vec!(4.0, 5.0, 6.0, 7.0)),
 			],
-		}];
-		let x = State::haploid_generation(y);
+		};
+		let mut state = State::new(vec![(y, vec![0.0])], false, 0);
+		let x = state.density_regulation(vec![vec![1.0, 1.0]], &[2], false, false);
 		dbg!(x);
 	}
 
@@ -152,37 +1055,372 @@ mod tests {
 		let y = vec![
 			Patch {
 				individuals: vec![
-					Individual {
-						loci: tiny_vec!(0.0),
-					},
-					Individual {
-						loci: tiny_vec!(1.0),
-					},
+					Individual::new(tiny_vec!(0.0)),
+					Individual::new(tiny_vec!(1.0)),
 				],
 			},
 			Patch {
 				individuals: vec![
-					Individual {
-						loci: tiny_vec!(2.0),
-					},
-					Individual {
-						loci: tiny_vec!(3.0),
-					},
+					Individual::new(tiny_vec!(2.0)),
+					Individual::new(tiny_vec!(3.0)),
 				],
 			},
 		];
-		let x = State::dispersal(y, 0.75);
+		let mut state = State::new(vec![], false, 0);
+		let x = state.dispersal(y, 0.75, &Dispersal::Global);
 		dbg!(x);
 	}
 
+	#[test]
+	fn dispersal_matrix() {
+		let chain = Dispersal::SteppingStone { circular: false }
+			.matrix(3)
+			.unwrap();
+		assert_eq!(
+			chain,
+			vec![
+				vec![0.0, 1.0, 0.0],
+				vec![1.0, 0.0, 1.0],
+				vec![0.0, 1.0, 0.0]
+			]
+		);
+		let ring = Dispersal::SteppingStone { circular: true }
+			.matrix(4)
+			.unwrap();
+		assert_eq!(ring[0], vec![0.0, 1.0, 0.0, 1.0]);
+		let lattice = Dispersal::Lattice {
+			width:    2,
+			circular: false,
+		}
+		.matrix(4)
+		.unwrap();
+		assert_eq!(lattice[0], vec![0.0, 1.0, 1.0, 0.0]);
+		// an incomplete last row still wraps around to the first row
+		let torus = Dispersal::Lattice {
+			width:    2,
+			circular: true,
+		}
+		.matrix(5)
+		.unwrap();
+		assert_eq!(torus[0], vec![0.0, 1.0, 1.0, 0.0, 1.0]);
+		assert!(Dispersal::Global.matrix(4).is_none());
+
+		let state = State::new(vec![], false, 0);
+		let mut config = config();
+		config.dispersal = Dispersal::Kernel { scale: 1.0 };
+		assert!(config.validate(&state).is_ok());
+		config.dispersal = Dispersal::Kernel { scale: f64::NAN };
+		assert!(config.validate(&state).is_err());
+	}
+
+	#[test]
+	fn topological_dispersal() {
+		let y: Vec<_> = (0 .. 5)
+			.map(|i| Patch::new(vec![Individual::new(tiny_vec!(i as f64)); i + 1]))
+			.collect();
+		let mut state = State::new(vec![], false, 0);
+		let x = state.dispersal(y, 1.0, &Dispersal::Kernel { scale: 1.0 });
+		let sizes: Vec<_> = x.iter().map(|patch| patch.len()).collect();
+		assert_eq!(sizes, vec![1, 2, 3, 4, 5]);
+
+		// on a chain every disperser ends up in a neighbouring patch or stays home
+		let y: Vec<_> = (0 .. 10)
+			.map(|i| Patch::new(vec![Individual::new(tiny_vec!(i as f64)); 100]))
+			.collect();
+		let x = state.dispersal(y, 0.5, &Dispersal::SteppingStone { circular: false });
+		for (index, patch) in x.iter().enumerate() {
+			assert!(patch
+				.iter()
+				.all(|individual| (individual[0] - index as f64).abs() <= 1.0));
+		}
+	}
+
+	#[test]
+	fn evolving_dispersal() {
+		let y: Vec<_> = (0 .. 4)
+			.map(|i| {
+				Patch::new(vec![
+					Individual {
+						loci: tiny_vec!(i as f64),
+						dispersal: Some(0.0),
+						..Default::default()
+					};
+					5
+				])
+			})
+			.collect();
+		let mut state = State::new(vec![], false, 0);
+		// nobody leaves when every individual carries a dispersal probability of zero
+		let x = state.dispersal(y.clone(), 1.0, &Dispersal::Global);
+		for (i, patch) in x.iter().enumerate() {
+			assert!(patch.iter().all(|individual| individual[0] == i as f64));
+		}
+		let x = state.dispersal(y, 1.0, &Dispersal::SteppingStone { circular: true });
+		for (i, patch) in x.iter().enumerate() {
+			assert!(patch.iter().all(|individual| individual[0] == i as f64));
+		}
+
+		let y = vec![Patch::new(vec![Default::default(); 100])];
+		let x = state.dispersal_mutation(y, 1.0, 2.0, 0.5);
+		assert!(x[0].iter().all(
+			|individual| matches!(individual.dispersal, Some(p) if (0.0 ..= 1.0).contains(&p))
+		));
+
+		// once the option is turned off everybody disperses with m again
+		let patches = (0 .. 4)
+			.map(|i| {
+				let individual = Individual {
+					loci: tiny_vec!(i as f64),
+					dispersal: Some(0.0),
+					..Default::default()
+				};
+				(Patch::new(vec![individual; 25]), vec![0.0])
+			})
+			.collect();
+		let mut state = State::new(patches, false, 0);
+		let mut config = config();
+		config.mutation_mu = 0.0;
+		step(&mut state, &config).unwrap();
+		assert!(state
+			.patches
+			.iter()
+			.flat_map(|(patch, _)| patch.iter())
+			.all(|individual| individual.dispersal.is_none()));
+		assert!(state
+			.patches
+			.iter()
+			.enumerate()
+			.any(|(i, (patch, _))| patch.iter().any(|individual| individual[0] != i as f64)));
+	}
+
+	#[test]
+	fn markers() {
+		let marked = |locus: f64, allele: i64| Individual {
+			markers: vec![allele; 2],
+			..Individual::new(tiny_vec!([f64; 10] => locus))
+		};
+		let mut state = State::new(
+			vec![
+				(Patch::new(vec![marked(0.0, 1); 10]), vec![0.0]),
+				(Patch::new(vec![marked(1.0, 2); 10]), vec![0.0]),
+			],
+			false,
+			0,
+		);
+		// the patches are fixed for different alleles and trait values
+		assert_eq!(stats::fst(&state), Some(1.0));
+		assert_eq!(stats::qst(&state), Some(1.0));
+		state.patches[0].0.individuals = vec![marked(0.0, 1), marked(1.0, 2)];
+		state.patches[1].0.individuals = vec![marked(1.0, 1), marked(0.0, 2)];
+		assert_eq!(stats::fst(&state), Some(0.0));
+		assert_eq!(stats::qst(&state), Some(0.0));
+
+		let markers = Markers {
+			loci:  3,
+			mu:    1.0,
+			model: MarkerModel::InfiniteAlleles,
+		};
+		state.markers(None);
+		assert_eq!(stats::fst(&state), None);
+		state.diploid = true;
+		state.markers(Some(&markers));
+		assert!(state.patches[0]
+			.0
+			.iter()
+			.all(|individual| individual.markers.len() == 6));
+
+		// every mutation creates a new allele
+		let y = vec![state.patches[0].0.clone()];
+		let x = state.marker_mutation(y, &markers);
+		let alleles: Vec<_> = x[0]
+			.iter()
+			.flat_map(|i| i.markers.iter())
+			.unique()
+			.collect();
+		assert_eq!(alleles.len(), 12);
+		// the new alleles keep counting up in long runs
+		state.alleles = i32::MAX as i64;
+		let x = state.marker_mutation(vec![state.patches[0].0.clone()], &markers);
+		assert!(x[0]
+			.iter()
+			.flat_map(|i| i.markers.iter())
+			.all(|&allele| allele > i32::MAX as i64));
+
+		// every offspring carries one marker haplotype of each parent
+		let parents = Patch::new(vec![
+			Individual {
+				markers: vec![1, 1, 1, 2, 2, 2],
+				..Individual::new(tiny_vec!(0.0, 0.0))
+			},
+			Individual {
+				markers: vec![3, 3, 3, 4, 4, 4],
+				..Individual::new(tiny_vec!(0.0, 0.0))
+			},
+		]);
+		let mut state = State::new(vec![(parents.clone(), vec![0.0])], true, 0);
+		let offspring = state.recombination(vec![parents], 0.5);
+		let markers = &offspring[0][0].markers;
+		assert!(markers[.. 3]
+			.iter()
+			.all(|&allele| allele == 1 || allele == 2));
+		assert!(markers[3 ..]
+			.iter()
+			.all(|&allele| allele == 3 || allele == 4));
+	}
+
+	#[test]
+	fn statistics() {
+		let summary = stats::summary(&[vec![0.0, 2.0], vec![4.0, 6.0], vec![]]);
+		assert_eq!(summary.patches[0].unwrap().mean, 1.0);
+		assert_eq!(summary.patches[2], None);
+		let global = summary.global.unwrap();
+		assert_eq!((global.mean, global.variance), (3.0, 5.0));
+		assert_eq!((summary.between, summary.within), (4.0, 1.0));
+
+		let individuals = vec![
+			Individual::new(tiny_vec!(0.0, 0.1, 0.1, 0.0)),
+			Individual::new(tiny_vec!(0.1, 0.0, 0.0, 0.1)),
+		];
+		let state = State::new(vec![(Patch::new(individuals), vec![0.0])], true, 0);
+		let loci = stats::loci(&state);
+		assert_eq!(loci.len(), 2);
+		assert!((loci[0].global.unwrap().mean - 0.05).abs() < 1e-12);
+		assert!((stats::phenotype(&state).global.unwrap().mean - 0.2).abs() < 1e-12);
+
+		// every individual carries two alleles that occur equally often
+		let heterozygosity = stats::heterozygosity(&state, 0.01).unwrap();
+		assert!((heterozygosity.expected - 0.5).abs() < 1e-12);
+		assert_eq!(heterozygosity.observed, Some(1.0));
+		assert!(stats::heterozygosity(&state, 0.0).is_none());
+
+		// the two loci always carry opposite alleles
+		let ld = stats::linkage_disequilibrium(&state);
+		assert!((ld[0][1] + 1.0).abs() < 1e-12 && (ld[0][0] - 1.0).abs() < 1e-12);
+
+		let mut state = state;
+		let mut config = config();
+		config.r_max = 2.0;
+		step(&mut state, &config).unwrap();
+		let fitness = stats::fitness(&state);
+		assert!(fitness.global.unwrap().mean < 2.0);
+	}
+
+	#[test]
+	fn morphs() {
+		let mut rng = ChaCha8Rng::seed_from_u64(0);
+		let normal = Normal::new(0.0, 0.05).unwrap();
+		let unimodal: Vec<f64> = (0 .. 2000).map(|_| normal.sample(&mut rng)).collect();
+		assert_eq!(stats::mixture(&unimodal, 4).len(), 1);
+		// a unimodal population on the allele grid is not split up into spikes
+		let grid: Vec<f64> = unimodal.iter().map(|x| (x / 0.01).round() * 0.01).collect();
+		assert_eq!(stats::mixture(&grid, 4).len(), 1);
+
+		let bimodal: Vec<f64> = (0 .. 2000)
+			.map(|i| normal.sample(&mut rng) + if i % 4 == 0 { -0.5 } else { 0.5 })
+			.collect();
+		let morphs = stats::mixture(&bimodal, 4);
+		assert_eq!(morphs.len(), 2);
+		assert!((morphs[0].mean + 0.5).abs() < 0.02 && (morphs[1].mean - 0.5).abs() < 0.02);
+		assert!((morphs[0].frequency - 0.25).abs() < 0.03);
+
+		assert_eq!(stats::mixture(&[0.3; 10], 4).len(), 1);
+		assert!(stats::mixture(&[], 4).is_empty());
+
+		let individuals = bimodal
+			.iter()
+			.map(|&x| Individual::new(tiny_vec!([f64; 10] => x)))
+			.collect();
+		let mut state = State::new(vec![(Patch::new(individuals), vec![0.0])], false, 0);
+		state.tick = 42;
+		let mut detector = stats::MorphDetector::new(3);
+		assert_eq!(detector.detect(&state).len(), 2);
+		state.tick = 43;
+		detector.detect(&state);
+		assert_eq!(detector.branching, Some(42));
+	}
+
+	#[test]
+	fn mutation_models() {
+		let y = vec![Patch::new(vec![Individual::new(tiny_vec!(0.0, 0.0)); 100])];
+		let mut state = State::new(vec![], false, 0);
+
+		let x = state.mutation(y.clone(), 1.0, 0.1, 0.1, &MutationModel::Stepwise, None);
+		assert!(x[0]
+			.iter()
+			.flat_map(|i| i.iter())
+			.all(|l| (l.abs() - 0.1).abs() < 1e-12));
+
+		let bounded = MutationModel::Bounded {
+			min: -0.05,
+			max: 0.05,
+		};
+		let x = state.mutation(y.clone(), 1.0, 1.0, 0.01, &bounded, None);
+		assert!(x[0]
+			.iter()
+			.flat_map(|i| i.iter())
+			.all(|l| l.abs() <= 0.05 + 1e-12));
+
+		// only the first locus mutates
+		let rates = [1.0, 0.0];
+		let model = MutationModel::HouseOfCards;
+		let x = state.mutation(y, 0.0, 0.1, 0.1, &model, Some(&rates));
+		assert!(x[0].iter().all(|i| i[0] != 0.0 && i[1] == 0.0));
+	}
+
 	#[test]
 	fn mutation() {
 		let y = vec![Patch {
-			individuals: vec![Individual {
-				loci: tiny_vec!(0.0, 0.0, 0.0, 0.0),
-			}],
+			individuals: vec![Individual::new(tiny_vec!(0.0, 0.0, 0.0, 0.0))],
 		}];
-		let x = State::mutation(y, 0.5, 0.1, 0.1);
+		let mut state = State::new(vec![], false, 0);
+		let x = state.mutation(y, 0.5, 0.1, 0.1, &MutationModel::DiscreteGaussian, None);
 		dbg!(x);
 	}
+
+	#[test]
+	fn mutator() {
+		let y = vec![Patch::new(vec![
+			Individual {
+				loci: tiny_vec!(0.0, 0.0, 0.0, 0.0),
+				mutation: Some(0.0),
+				..Default::default()
+			},
+			Individual {
+				loci: tiny_vec!(0.0, 0.0, 0.0, 0.0),
+				mutation: Some(1.0),
+				..Default::default()
+			},
+		])];
+		let mut state = State::new(vec![], false, 0);
+		// the mutator replaces the global mutation probability
+		let x = state.mutation(y, 0.5, 1.0, 0.1, &MutationModel::Gaussian, None);
+		assert!(x[0][0].iter().all(|&locus| locus == 0.0));
+		assert!(x[0][1].iter().all(|&locus| locus != 0.0));
+
+		let y = vec![Patch::new(vec![Default::default(); 100])];
+		let x = state.mutator_mutation(y, 1.0, 2.0);
+		assert!(x[0].iter().all(
+			|individual| matches!(individual.mutation, Some(p) if (0.0 ..= 1.0).contains(&p))
+		));
+
+		// once the option is turned off everybody mutates with mutation_mu again
+		let individual = Individual {
+			loci: tiny_vec!(0.0),
+			mutation: Some(0.0),
+			..Default::default()
+		};
+		let mut state = State::new(
+			vec![(Patch::new(vec![individual; 100]), vec![0.0])],
+			false,
+			0,
+		);
+		let mut config = config();
+		config.mutation_mu = 1.0;
+		config.mutation_model = MutationModel::Stepwise;
+		step(&mut state, &config).unwrap();
+		assert!(state.patches[0]
+			.0
+			.iter()
+			.all(|individual| individual.mutation.is_none() && individual[0] != 0.0));
+	}
 }
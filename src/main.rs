@@ -9,17 +9,19 @@ use warp::ws::{Message, WebSocket};
 use warp::{Filter, Reply};
 
 use rand::seq::SliceRandom;
-use simulation::{init, step, Config, InitConfig, Patch, Individual};
+use simulation::{init, stats, step, Config, InitConfig, State, Individual};
 use rand::prelude::IteratorRandom;
 
 static ERROR: &str = "Internal server error, an illegal message was received.";
 static DROPPED: &str = "The receiver on the simulation thread were dropped, most likely due to a crash. Please refresh the page or restart.";
-static NAN: &str = "Encountered NaN in loci or population size has become 0, stopping simulation.";
+static NAN: &str = "Encountered NaN in loci, stopping simulation.";
 static WS: &str =
 	"Websocket was closed while the simulation thread was still running, stopping simulation.";
 
 static SAMPLE_SIZE: usize = 100;
 static INTERVAL: u64 = 100;
+static AGE_CLASSES: usize = 10;
+static MORPHS: usize = 4;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct GraphData {
@@ -28,6 +30,17 @@ pub struct GraphData {
 	pub phenotype_sample: Vec<(usize, f64)>, // (patch_index, phenotype)
 	pub environment: Vec<f64>,
 	pub loci: Vec<Vec<(usize, f64)>>,
+	pub heritability: f64,
+	pub generation_time: f64,
+	pub age_distribution: Vec<f64>, // fraction per age, the last class holds all older ages
+	pub dormant: f64, // size of the seed bank relative to the population
+	pub occupancy: Vec<f64>, // size of every patch relative to its carrying capacity
+	pub fst: f64,
+	pub qst: f64,
+	pub mean_fitness: f64,
+	pub heterozygosity: f64, // expected heterozygosity on the mutation step grid
+	pub morphs: Vec<(f64, f64)>, // (mean, frequency) of every morph
+	pub branching: Option<u64>, // tick at which several morphs were first detected
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -55,7 +68,12 @@ pub enum Response {
 	Error(String),
 }
 
-fn extract_graph_data(patches: &[(Patch, f64)]) -> Option<GraphData> {
+fn extract_graph_data(
+	state: &State,
+	config: &Config,
+	detector: &mut stats::MorphDetector,
+) -> Option<GraphData> {
+	let patches = &state.patches;
 	let phenotypes: Vec<_> = patches
 		.iter()
 		.enumerate()
@@ -63,21 +81,12 @@ fn extract_graph_data(patches: &[(Patch, f64)]) -> Option<GraphData> {
 			patch
 				.individuals
 				.iter()
-				.map(move |indiv| (index, indiv.phenotype()))
+				.map(move |indiv| (index, state.expressed(indiv, index)[0]))
 		})
 		.flatten()
 		.collect();
 
-	let mean = match phenotypes.len() {
-		0 => None,
-		_ => Some(phenotypes.iter().map(|(_, p)| p).sum::<f64>() / phenotypes.len() as f64),
-	}?;
-
-	let variance = phenotypes
-		.iter()
-		.map(|(_, f)| (f - mean) * (f - mean))
-		.sum::<f64>()
-		/ phenotypes.len() as f64;
+	let variance = stats::phenotype(state).global?.variance;
 
 	let max = phenotypes
 		.iter()
@@ -99,6 +108,16 @@ fn extract_graph_data(patches: &[(Patch, f64)]) -> Option<GraphData> {
 		.map(|index| individuals.iter().map(|(indiv, patchi)| (*patchi, indiv.loci[index])).collect())
 		.collect();
 
+	let mut age_distribution = state.age_distribution();
+	if age_distribution.len() > AGE_CLASSES {
+		let older: usize = age_distribution.drain(AGE_CLASSES..).sum();
+		age_distribution[AGE_CLASSES - 1] += older;
+	}
+	let age_distribution = age_distribution
+		.into_iter()
+		.map(|count| count as f64 / phenotypes.len() as f64)
+		.collect();
+
 	Some(GraphData {
 		phenotype_variance: variance,
 		phenotype_distance: max - min,
@@ -106,8 +125,31 @@ fn extract_graph_data(patches: &[(Patch, f64)]) -> Option<GraphData> {
 			.choose_multiple(&mut rand::thread_rng(), SAMPLE_SIZE)
 			.cloned()
 			.collect(),
-		environment: patches.iter().map(|x| x.1).collect(),
+		environment: patches.iter().map(|x| x.1[0]).collect(),
 		loci,
+		// without phenotypic variance there is nothing to inherit
+		heritability: state.heritability.unwrap_or(0.0),
+		generation_time: state.generation_time.unwrap_or(0.0),
+		age_distribution,
+		dormant: state.dormant.iter().map(|pool| pool.len()).sum::<usize>() as f64
+			/ phenotypes.len() as f64,
+		occupancy: patches
+			.iter()
+			.zip(&state.capacities)
+			.map(|((patch, _), &capacity)| patch.len() as f64 / capacity as f64)
+			.collect(),
+		// without variation there is no differentiation
+		fst: state.fst.unwrap_or(0.0),
+		qst: state.qst.unwrap_or(0.0),
+		mean_fitness: stats::fitness(state).global.map_or(0.0, |fitness| fitness.mean),
+		heterozygosity: stats::heterozygosity(state, config.mutation_step)
+			.map_or(0.0, |heterozygosity| heterozygosity.expected),
+		morphs: detector
+			.detect(state)
+			.into_iter()
+			.map(|morph| (morph.mean, morph.frequency))
+			.collect(),
+		branching: detector.branching,
 	})
 }
 
@@ -139,9 +181,25 @@ fn simulate(
 	info!("new simulation thread started");
 
 	let ticks = initial.t_max.unwrap_or(u64::MAX);
-	let mut state = init(initial, config.environment.clone()).unwrap();
+	let mut state = match init(initial, config.environment.clone()) {
+		Ok(state) => state,
+		Err(error) => {
+			blocking_respond(&sender, Response::Error(error.to_string()));
+			return;
+		}
+	};
+
+	if let Err(error) = config.validate(&state) {
+		blocking_respond(&sender, Response::Error(error.to_string()));
+		return;
+	}
+	blocking_respond(
+		&sender,
+		Response::Info(format!("Simulation started with seed {}", state.seed)),
+	);
 
 	let mut paused = false;
+	let mut detector = stats::MorphDetector::new(MORPHS);
 
 	let mut last = Instant::now();
 	let interval = Duration::from_millis(INTERVAL);
@@ -164,7 +222,10 @@ fn simulate(
 				// );
 				return;
 			}
-			Ok(Notification::Update(new)) => config = new,
+			Ok(Notification::Update(new)) => match new.validate(&state) {
+				Ok(()) => config = new,
+				Err(error) => blocking_respond(&sender, Response::Error(error.to_string())),
+			},
 			Ok(Notification::Pause) => paused = true,
 			Ok(Notification::Resume) => paused = false,
 			Err(std::sync::mpsc::TryRecvError::Empty) => (),
@@ -176,15 +237,34 @@ fn simulate(
 			continue;
 		}
 
-		step(&mut state, &config);
+		if let Err(error) = step(&mut state, &config) {
+			blocking_respond(&sender, Response::Error(error.to_string()));
+			return;
+		}
 		state.tick += 1;
 
+		// with population dynamics the patches can empty out, which ends the simulation
+		if state.patches.iter().all(|(patch, _)| patch.is_empty()) {
+			blocking_respond(
+				&sender,
+				Response::Info(format!("The population has gone extinct at tick {}", state.tick)),
+			);
+			return;
+		}
+
 		if last.elapsed() > interval {
 			debug!("sending state {}", state.tick);
 			std::thread::yield_now();
 			last = Instant::now();
 
-			let data = extract_graph_data(&state.patches).expect(NAN);
+			let branched = detector.branching.is_some();
+			let data = extract_graph_data(&state, &config, &mut detector).expect(NAN);
+			if let (false, Some(tick)) = (branched, detector.branching) {
+				blocking_respond(
+					&sender,
+					Response::Info(format!("Branching into several morphs was first detected at tick {}", tick)),
+				);
+			}
 			blocking_respond(&sender, Response::State(state.tick, data));
 		}
 	}